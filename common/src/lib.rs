@@ -5,6 +5,28 @@ use strict_num::FiniteF64;
 pub mod value;
 
 pub fn change_per_second(m: i128, n: i128, p: Duration) -> Option<FiniteF64> {
+    // No change is no rate even between samples taken at the same time
+    if m == n {
+        return FiniteF64::new(0.);
+    }
     let change = (n - m) as f64 / p.as_secs_f64();
     FiniteF64::new(change)
 }
+
+/// How the change of a counter between two samples is reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChangeMode {
+    /// The change divided by the time between the samples
+    #[default]
+    PerSecond,
+    /// The raw change between the samples
+    Total,
+}
+impl ChangeMode {
+    pub fn change(&self, m: i128, n: i128, p: Duration) -> Option<FiniteF64> {
+        match self {
+            ChangeMode::PerSecond => change_per_second(m, n, p),
+            ChangeMode::Total => FiniteF64::new((n - m) as f64),
+        }
+    }
+}
@@ -7,6 +7,9 @@ pub struct FloatColorStatsDisplay<'a> {
     pub values: &'a [FiniteF64],
     pub width: usize,
     pub postfix: FloatDisplayPostfix,
+    pub limit: FloatDisplayLimit,
+    pub format: NumberFormat,
+    pub align: NumberAlign,
 }
 impl<'a> fmt::Display for FloatColorStatsDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -19,15 +22,31 @@ impl<'a> fmt::Display for FloatColorStatsDisplay<'a> {
                 if round_half_to_even(*v, self.width, limit) {
                     return zero_int_stat_color();
                 }
-                if v.get() <= -10.0 {
-                    return extreme_percent_color();
-                }
-                if v.get() <= -5.0 {
-                    return warn_percent_color();
+                match self.limit {
+                    FloatDisplayLimit::ExtremeLow => {
+                        if v.get() <= -10.0 {
+                            return extreme_percent_color();
+                        }
+                        if v.get() <= -5.0 {
+                            return warn_percent_color();
+                        }
+                    }
+                    FloatDisplayLimit::ExtremeHigh { extreme } => {
+                        if extreme <= v.get() {
+                            return extreme_percent_color();
+                        }
+                        return warn_percent_color();
+                    }
+                    FloatDisplayLimit::Signed => {
+                        if 0.0 < v.get() {
+                            return extreme_percent_color();
+                        }
+                    }
                 }
                 int_stat_color()
             };
 
+            start_column(f)?;
             match self.postfix {
                 FloatDisplayPostfix::Unit(unit) => write!(
                     f,
@@ -37,13 +56,19 @@ impl<'a> fmt::Display for FloatColorStatsDisplay<'a> {
                         width: self.width,
                         value: *v,
                         unit,
+                        sign: matches!(self.limit, FloatDisplayLimit::Signed),
                     }
                 )?,
                 FloatDisplayPostfix::Decimals(decimals) => write!(
                     f,
-                    "{start} {value:width$.decimals$}{end}",
-                    value = v.get(),
-                    width = self.width,
+                    "{start} {value}{end}",
+                    value = NumberDisplay {
+                        value: v.get(),
+                        width: self.width,
+                        decimals,
+                        format: self.format,
+                        align: self.align,
+                    },
                     start = color_start(),
                     end = normal_color()
                 )?,
@@ -53,6 +78,15 @@ impl<'a> fmt::Display for FloatColorStatsDisplay<'a> {
     }
 }
 #[derive(Debug, Clone, Copy)]
+pub enum FloatDisplayLimit {
+    /// Highlight large negative values
+    ExtremeLow,
+    /// Highlight any non-zero value; values at or above `extreme` are highlighted the most
+    ExtremeHigh { extreme: f64 },
+    /// Highlight positive values the most, and write their `+` sign
+    Signed,
+}
+#[derive(Debug, Clone, Copy)]
 pub enum FloatDisplayPostfix {
     Unit(MemoryUnit),
     Decimals(usize),
@@ -81,6 +115,8 @@ pub struct PercentageColorStatsDisplay<'a> {
     pub width: usize,
     pub decimals: usize,
     pub limit: PercentageDisplayLimit,
+    pub format: NumberFormat,
+    pub align: NumberAlign,
 }
 impl<'a> fmt::Display for PercentageColorStatsDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -138,12 +174,17 @@ impl<'a> fmt::Display for PercentageColorStatsDisplay<'a> {
                 int_stat_color()
             };
 
+            start_column(f)?;
             write!(
                 f,
-                "{start} {value:width$.decimals$}{end}",
-                value = v,
-                width = width,
-                decimals = self.decimals,
+                "{start} {value}{end}",
+                value = NumberDisplay {
+                    value: v,
+                    width,
+                    decimals: self.decimals,
+                    format: self.format,
+                    align: self.align,
+                },
                 start = color_start(),
                 end = normal_color()
             )?;
@@ -172,6 +213,7 @@ impl<'a> fmt::Display for U64ColorStatsDisplay<'a> {
                 }
                 int_stat_color()
             };
+            start_column(f)?;
             match self.unit {
                 Some(unit) => write!(
                     f,
@@ -180,7 +222,8 @@ impl<'a> fmt::Display for U64ColorStatsDisplay<'a> {
                         color: color_start(),
                         width: self.width,
                         value: FiniteF64::new((*v) as f64).unwrap(),
-                        unit
+                        unit,
+                        sign: false,
                     }
                 )?,
                 None => write!(
@@ -196,12 +239,77 @@ impl<'a> fmt::Display for U64ColorStatsDisplay<'a> {
     }
 }
 
+/// How floating point values are rendered
+///
+/// Neither format is locale-aware: the decimal separator is always `.` and there are no thousands separators.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum NumberFormat {
+    /// e.g. `1234.50`
+    #[default]
+    Fixed,
+    /// e.g. `1.23e3`
+    Scientific,
+}
+
+/// How numbers line up in a column
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum NumberAlign {
+    /// On the last character
+    #[default]
+    Right,
+    /// On the decimal point, or where it would be
+    Decimal,
+}
+
+struct NumberDisplay {
+    pub value: f64,
+    pub width: usize,
+    pub decimals: usize,
+    pub format: NumberFormat,
+    pub align: NumberAlign,
+}
+impl fmt::Display for NumberDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (value, width, decimals) = (self.value, self.width, self.decimals);
+        match self.align {
+            NumberAlign::Right => match self.format {
+                NumberFormat::Fixed => write!(f, "{value:width$.decimals$}"),
+                NumberFormat::Scientific => write!(f, "{value:width$.decimals$e}"),
+            },
+            NumberAlign::Decimal => {
+                let number = match self.format {
+                    NumberFormat::Fixed => format!("{value:.decimals$}"),
+                    NumberFormat::Scientific => format!("{value:.decimals$e}"),
+                };
+                // Reserve room for the point, the decimals and a short exponent like `e-5`; longer
+                // exponents overflow the column
+                let fraction_width = match (self.format, decimals) {
+                    (NumberFormat::Fixed, 0) => 0,
+                    (NumberFormat::Fixed, _) => decimals + 1,
+                    (NumberFormat::Scientific, 0) => 3,
+                    (NumberFormat::Scientific, _) => decimals + 1 + 3,
+                };
+                // Keep a digit in front of the point
+                let fraction_width = fraction_width.min(width.saturating_sub(1));
+                let integer_width = width - fraction_width;
+                let split = number.find(['.', 'e']).unwrap_or(number.len());
+                let (integer, fraction) = number.split_at(split);
+                write!(f, "{integer:>integer_width$}{fraction:<fraction_width$}")
+            }
+        }
+    }
+}
+
 struct ValueUnitDisplay {
     pub color: &'static str,
     /// Width of overall display including the unit char
     pub width: usize,
     pub value: FiniteF64,
     pub unit: MemoryUnit,
+    /// Whether to write the `+` of positive values
+    pub sign: bool,
 }
 impl fmt::Display for ValueUnitDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -210,10 +318,14 @@ impl fmt::Display for ValueUnitDisplay {
             None => (self.value, self.unit),
         };
         let width = self.width.saturating_sub(unit.as_str().len());
+        let value = value.get();
+        let value = match self.sign {
+            true => format!("{value:+.1}"),
+            false => format!("{value:.1}"),
+        };
         write!(
             f,
-            "{start} {value:width$.1}{end}{unit}",
-            value = value.get(),
+            "{start} {value:>width$}{end}{unit}",
             start = self.color,
             end = normal_color(),
             unit = unit.as_str()
@@ -275,15 +387,72 @@ pub const fn normal_color() -> &'static str {
     NORMAL
 }
 
-#[allow(dead_code)]
+/// Starts a column in the alternate form `{:#}` of the displays, which marks the columns so that
+/// they can be delimited by a separator instead of aligned by their padding
+pub const COLUMN_START: char = '\x1f';
+
+/// Mark the start of a column if `f` is in the alternate form
+pub fn start_column(f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match f.alternate() {
+        true => fmt::Write::write_char(f, COLUMN_START),
+        false => Ok(()),
+    }
+}
+
+/// Write the columns of `display` in the form of `f`
+pub fn write_columns(f: &mut fmt::Formatter<'_>, display: &impl fmt::Display) -> fmt::Result {
+    match f.alternate() {
+        true => write!(f, "{display:#}"),
+        false => write!(f, "{display}"),
+    }
+}
+
+/// `text` without its color escapes
+pub fn strip_colors(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('\x1b') {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest.find('m') {
+            Some(end) => rest = &rest[end + 1..],
+            None => rest = "",
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+/// A color that stays the same for `pid` and likely differs from the ones of its neighbors
+pub fn pid_color(pid: usize) -> &'static str {
+    const PALETTE: &[&str] = &[
+        LIGHT_RED,
+        LIGHT_GREEN,
+        LIGHT_YELLOW,
+        LIGHT_BLUE,
+        LIGHT_MAGENTA,
+        LIGHT_CYAN,
+        BOLD_RED,
+        BOLD_GREEN,
+        BOLD_YELLOW,
+        BOLD_BLUE,
+        BOLD_MAGENTA,
+        BOLD_CYAN,
+    ];
+    // Fibonacci hashing spreads consecutive PIDs across the palette
+    let hash = (pid as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    PALETTE[((hash >> 32) % PALETTE.len() as u64) as usize]
+}
+
 const LIGHT_RED: &str = "\x1b[31;22m";
 const BOLD_RED: &str = "\x1b[31;1m";
 const LIGHT_GREEN: &str = "\x1b[32;22m";
-#[allow(dead_code)]
 const BOLD_GREEN: &str = "\x1b[32;1m";
-#[allow(dead_code)]
 const LIGHT_YELLOW: &str = "\x1b[33;22m";
+const BOLD_YELLOW: &str = "\x1b[33;1m";
+const LIGHT_MAGENTA: &str = "\x1b[35;22m";
 const BOLD_MAGENTA: &str = "\x1b[35;1m";
 const BOLD_BLUE: &str = "\x1b[34;1m";
 const LIGHT_BLUE: &str = "\x1b[34;22m";
+const LIGHT_CYAN: &str = "\x1b[36;22m";
+const BOLD_CYAN: &str = "\x1b[36;1m";
 const NORMAL: &str = "\x1b[0m";
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+/// Halves the interval while the monitored processes are busy and doubles it while they are idle
+#[derive(Debug, Clone)]
+pub struct AdaptiveInterval {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+impl AdaptiveInterval {
+    /// Start at `interval`, clamped to `[min, max]`
+    pub fn new(interval: Duration, min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            current: interval.clamp(min, max.max(min)),
+        }
+    }
+
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Return the next interval given whether any process was busy over the previous one
+    pub fn next(&mut self, busy: bool) -> Duration {
+        self.current = match busy {
+            true => (self.current / 2).max(self.min),
+            false => self.current.saturating_mul(2).min(self.max.max(self.min)),
+        };
+        self.current
+    }
+}
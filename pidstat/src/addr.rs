@@ -0,0 +1,101 @@
+use core::fmt;
+use std::{num::NonZeroU64, time::Instant};
+
+use common::value::{
+    int_stat_color, normal_color, start_column, write_columns, zero_int_stat_color,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::process::{
+    CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+    TidDisplayOption,
+};
+
+/// Addresses from `/proc/<pid>/stat`.
+///
+/// The kernel reports zero, shown as `-`, for the ones the reader is not allowed to see.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressStats {
+    /// The bottom of the stack
+    pub start_stack: Option<NonZeroU64>,
+    /// The current stack pointer
+    pub kstk_esp: Option<NonZeroU64>,
+    /// The current instruction pointer
+    pub kstk_eip: Option<NonZeroU64>,
+    /// The kernel function the task is waiting in
+    pub wchan: Option<NonZeroU64>,
+    #[serde(skip, default = "Instant::now")]
+    pub time: Instant,
+}
+
+/// `0x` and 16 hexadecimal digits
+const WIDTH: usize = 18;
+
+#[derive(Debug, Clone)]
+pub struct AddressStatsHeaderDisplay {
+    pub tid: TidDisplayOption,
+}
+impl fmt::Display for AddressStatsHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        for name in ["StartStack", "KStkESP", "KStkEIP", "WChan"] {
+            start_column(f)?;
+            write!(f, " {name:>WIDTH$}")?;
+        }
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AddressStatsValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub curr_stats: &'a AddressStats,
+}
+impl<'a> fmt::Display for AddressStatsValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+
+        for address in [
+            self.curr_stats.start_stack,
+            self.curr_stats.kstk_esp,
+            self.curr_stats.kstk_eip,
+            self.curr_stats.wchan,
+        ] {
+            start_column(f)?;
+            match address {
+                Some(address) => write!(
+                    f,
+                    "{start} {address:#0WIDTH$x}{end}",
+                    start = int_stat_color(),
+                    end = normal_color()
+                )?,
+                None => write!(
+                    f,
+                    "{start} {value:>WIDTH$}{end}",
+                    start = zero_int_stat_color(),
+                    value = '-',
+                    end = normal_color()
+                )?,
+            }
+        }
+
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
+
+        Ok(())
+    }
+}
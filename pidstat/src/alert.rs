@@ -0,0 +1,118 @@
+use core::fmt;
+use std::{collections::BTreeSet, time::Duration};
+
+use common::change_per_second;
+
+use crate::read::Stats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertMetric {
+    /// `%CPU`
+    Cpu,
+    /// `%MEM`
+    Mem,
+    /// `kB_rd/s` plus `kB_wr/s`
+    Io,
+}
+impl fmt::Display for AlertMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertMetric::Cpu => write!(f, "cpu"),
+            AlertMetric::Mem => write!(f, "mem"),
+            AlertMetric::Io => write!(f, "io"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    /// In percent of a CPU
+    pub cpu: Option<f64>,
+    /// In percent of the total memory
+    pub mem: Option<f64>,
+    /// In kB per second
+    pub io: Option<f64>,
+}
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.cpu.is_none() && self.mem.is_none() && self.io.is_none()
+    }
+
+    /// Return every metric of the process with its value in the unit of its threshold, and whether it exceeds that threshold
+    pub fn check(&self, prev: &Stats, curr: &Stats) -> Vec<(AlertMetric, f64, bool)> {
+        let mut checks = vec![];
+        let prev = &prev.components;
+        let curr = &curr.components;
+        if let (Some(threshold), Some(prev), Some(curr)) = (self.cpu, &prev.cpu, &curr.cpu) {
+            let ticks = |s: &crate::cpu::CpuStats| s.user_time + s.system_time + s.wait_time;
+            if let Some(rate) = rate(ticks(prev), ticks(curr), curr.time - prev.time) {
+                let value = rate / curr.clock_ticks_per_second as f64 * 100.;
+                checks.push((AlertMetric::Cpu, value, threshold < value));
+            }
+        }
+        if let (Some(threshold), Some(curr)) = (self.mem, &curr.mem) {
+            let value = curr.rss as f64 / curr.tot_mem as f64 * 100.;
+            checks.push((AlertMetric::Mem, value, threshold < value));
+        }
+        if let (Some(threshold), Some(prev), Some(curr)) = (self.io, &prev.io, &curr.io) {
+            let bytes = |s: &crate::io::IoStats| s.read_bytes + s.write_bytes;
+            if let Some(rate) = rate(bytes(prev), bytes(curr), curr.time - prev.time) {
+                let value = rate / 1024.;
+                checks.push((AlertMetric::Io, value, threshold < value));
+            }
+        }
+        checks
+    }
+}
+
+/// The change per second of a counter, or `None` if it went backwards, as when the PID was reused
+fn rate(prev: u64, curr: u64, interval: Duration) -> Option<f64> {
+    if curr < prev {
+        return None;
+    }
+    change_per_second(prev.into(), curr.into(), interval).map(|rate| rate.get())
+}
+
+/// Fires once when a metric of a process starts exceeding its threshold and stays quiet until it drops below it again
+#[derive(Debug, Clone, Default)]
+pub struct AlertDebouncer {
+    exceeding: BTreeSet<(usize, AlertMetric)>,
+}
+impl AlertDebouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return whether an alert should fire
+    pub fn update(&mut self, pid: usize, metric: AlertMetric, exceeded: bool) -> bool {
+        match exceeded {
+            true => self.exceeding.insert((pid, metric)),
+            false => {
+                self.exceeding.remove(&(pid, metric));
+                false
+            }
+        }
+    }
+
+    /// Forget every process for which `f` returns `false`
+    pub fn retain(&mut self, mut f: impl FnMut(usize) -> bool) {
+        self.exceeding.retain(|(pid, _)| f(*pid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_of_counters() {
+        let second = Duration::from_secs(1);
+        assert_eq!(rate(100, 150, second), Some(50.));
+        assert_eq!(rate(100, 150, second / 2), Some(100.));
+        assert_eq!(rate(100, 100, Duration::ZERO), Some(0.));
+        // Went backwards
+        assert_eq!(rate(150, 100, second), None);
+        // No time passed
+        assert_eq!(rate(100, 150, Duration::ZERO), None);
+    }
+}
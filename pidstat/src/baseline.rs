@@ -0,0 +1,170 @@
+use core::fmt;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use common::value::{
+    int_stat_color, item_name_color, normal_color, start_column, write_columns, zero_int_stat_color,
+};
+
+use crate::{
+    columns::ColumnHeaderDisplay,
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
+    record::SampleRecord,
+};
+
+/// Process samples of a previous run, keyed by command since PIDs change between runs
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    records: BTreeMap<String, SampleRecord>,
+}
+impl Baseline {
+    /// Load the NDJSON records written by [`Baseline::save`]
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut records = BTreeMap::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let record: SampleRecord = serde_json::from_str(&line?)?;
+            // Compare processes, not their threads
+            if record.tid.is_some() {
+                continue;
+            }
+            records.entry(record.command.clone()).or_insert(record);
+        }
+        Ok(Self { records })
+    }
+
+    /// Overwrite `path` with `records` as NDJSON
+    pub fn save<'a>(
+        path: &Path,
+        records: impl IntoIterator<Item = &'a SampleRecord>,
+    ) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for record in records {
+            serde_json::to_writer(&mut file, record)?;
+            writeln!(file)?;
+        }
+        file.flush()
+    }
+
+    pub fn get(&self, command: &str) -> Option<&SampleRecord> {
+        self.records.get(command)
+    }
+}
+
+/// The key metrics of a record with their names
+fn metrics(record: &SampleRecord) -> Vec<(&'static str, f64)> {
+    let mut metrics = vec![];
+    if let Some(cpu) = &record.cpu {
+        metrics.push(("%CPU", cpu.cpu));
+    }
+    if let Some(mem) = &record.mem {
+        metrics.push(("RSS", mem.rss as f64));
+        metrics.push(("%MEM", mem.mem));
+    }
+    if let Some(io) = &record.io {
+        metrics.push(("kB_rd", io.kb_rd));
+        metrics.push(("kB_wr", io.kb_wr));
+    }
+    if let Some(ctx_switch) = &record.ctx_switch {
+        metrics.push(("cswch", ctx_switch.cswch));
+        metrics.push(("nvcswch", ctx_switch.nvcswch));
+    }
+    metrics
+}
+
+#[derive(Debug, Clone)]
+pub struct BaselineComparisonHeaderDisplay {
+    pub tid: TidDisplayOption,
+}
+impl fmt::Display for BaselineComparisonHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        start_column(f)?;
+        write!(f, "  {:<7}", "Metric")?;
+        for name in ["Current", "Baseline", "Delta"] {
+            start_column(f)?;
+            write!(f, " {name:>10}")?;
+        }
+        let ratio = ColumnHeaderDisplay {
+            names: &["Ratio"],
+            width: 8,
+        };
+        write_columns(f, &ratio)?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+/// One row per metric of the current sample against the baseline sample of the same command
+#[derive(Debug, Clone)]
+pub struct BaselineComparisonValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub curr: &'a SampleRecord,
+    pub baseline: &'a SampleRecord,
+}
+impl<'a> fmt::Display for BaselineComparisonValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let baseline = metrics(self.baseline);
+        for (name, curr) in metrics(self.curr) {
+            let Some((_, baseline)) = baseline.iter().find(|(n, _)| *n == name) else {
+                continue;
+            };
+            let display = IdValueDisplay {
+                process: self.id,
+                tid: self.tid,
+                pid_color: self.pid_color,
+            };
+            write_columns(f, &display)?;
+            start_column(f)?;
+            write!(
+                f,
+                "{start}  {name:<7}{end}",
+                start = item_name_color(),
+                end = normal_color()
+            )?;
+            let delta = curr - baseline;
+            let start = match delta == 0. {
+                true => zero_int_stat_color(),
+                false => int_stat_color(),
+            };
+            start_column(f)?;
+            write!(f, "{start} {curr:10.2}{end}", end = normal_color())?;
+            start_column(f)?;
+            write!(f, "{start} {baseline:10.2}{end}", end = normal_color())?;
+            start_column(f)?;
+            write!(f, "{start} {delta:+10.2}{end}", end = normal_color())?;
+            start_column(f)?;
+            match *baseline == 0. {
+                true => write!(
+                    f,
+                    "{start} {value:>8}{end}",
+                    value = '-',
+                    end = normal_color()
+                )?,
+                false => write!(
+                    f,
+                    "{start} {ratio:7.2}x{end}",
+                    ratio = curr / baseline,
+                    end = normal_color()
+                )?,
+            }
+            let display = CommandDisplay {
+                process: self.id,
+                pid_color: self.pid_color,
+            };
+            write_columns(f, &display)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+/// The source of the time each sample is taken at, which the rates are computed over
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The monotonic clock of the system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when advanced, so that samples can be taken exactly some interval apart
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Cell<Instant>,
+}
+impl ManualClock {
+    pub fn new(start: Instant) -> Self {
+        Self {
+            now: Cell::new(start),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
@@ -0,0 +1,590 @@
+use core::fmt;
+
+use common::{value::start_column, ChangeMode};
+
+/// A column of the tables
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    /// The name of the column when the total change replaces the rate, as with `--cumulative`
+    pub total: Option<&'static str>,
+    /// The option that enables the column
+    pub flag: &'static str,
+    pub unit: &'static str,
+    pub description: &'static str,
+}
+
+/// Every column of the tables, in the order of the components, for rates per second
+pub const COLUMNS: &[Column] = &[
+    Column {
+        name: "UID",
+        total: None,
+        flag: "",
+        unit: "",
+        description: "The real user ID of the task",
+    },
+    Column {
+        name: "PID",
+        total: None,
+        flag: "",
+        unit: "",
+        description: "The ID of the process",
+    },
+    Column {
+        name: "TGID",
+        total: None,
+        flag: "-t",
+        unit: "",
+        description: "The ID of the thread group leader, on process rows",
+    },
+    Column {
+        name: "TID",
+        total: None,
+        flag: "-t",
+        unit: "",
+        description: "The ID of the thread, on thread rows",
+    },
+    Column {
+        name: "%usr",
+        total: Some("usr(s)"),
+        flag: "-u",
+        unit: "%",
+        description: "CPU used at the user level, without guest time",
+    },
+    Column {
+        name: "%system",
+        total: Some("sys(s)"),
+        flag: "-u",
+        unit: "%",
+        description: "CPU used at the system level",
+    },
+    Column {
+        name: "%guest",
+        total: Some("guest(s)"),
+        flag: "-u",
+        unit: "%",
+        description: "CPU spent running a virtual processor",
+    },
+    Column {
+        name: "%wait",
+        total: Some("wait(s)"),
+        flag: "-u",
+        unit: "%",
+        description: "CPU time spent waiting to run",
+    },
+    Column {
+        name: "%blkio",
+        total: Some("blk(s)"),
+        flag: "-u",
+        unit: "%",
+        description: "Time blocked on block I/O, with delay accounting",
+    },
+    Column {
+        name: "%CPU",
+        total: Some("CPU(s)"),
+        flag: "-u",
+        unit: "%",
+        description: "Total CPU used",
+    },
+    Column {
+        name: "%share",
+        total: None,
+        flag: "--cpu-mode system-relative",
+        unit: "%",
+        description: "Share of the busy time of all CPUs",
+    },
+    Column {
+        name: "%acct",
+        total: Some("acct(s)"),
+        flag: "--cpu-reconcile",
+        unit: "%",
+        description: "CPU accounted to the task in stat, with guest time",
+    },
+    Column {
+        name: "%sched",
+        total: Some("sched(s)"),
+        flag: "--cpu-reconcile",
+        unit: "%",
+        description: "CPU time the scheduler ran the task by schedstat",
+    },
+    Column {
+        name: "CPU",
+        total: None,
+        flag: "-u",
+        unit: "",
+        description: "The processor the task last ran on",
+    },
+    Column {
+        name: "minflt/s",
+        total: Some("minflt"),
+        flag: "-r",
+        unit: "faults/s",
+        description: "Minor faults, which did not load a page from disk",
+    },
+    Column {
+        name: "majflt/s",
+        total: Some("majflt"),
+        flag: "-r",
+        unit: "faults/s",
+        description: "Major faults, which loaded a page from disk",
+    },
+    Column {
+        name: "cminflt/s",
+        total: Some("cminflt"),
+        flag: "--children-faults",
+        unit: "faults/s",
+        description: "Minor faults of the children that were waited for",
+    },
+    Column {
+        name: "cmajflt/s",
+        total: Some("cmajflt"),
+        flag: "--children-faults",
+        unit: "faults/s",
+        description: "Major faults of the children that were waited for",
+    },
+    Column {
+        name: "VSZ",
+        total: None,
+        flag: "-r",
+        unit: "kB",
+        description: "Virtual memory size",
+    },
+    Column {
+        name: "RSS",
+        total: None,
+        flag: "-r",
+        unit: "kB",
+        description: "Resident set size, the non-swapped physical memory",
+    },
+    Column {
+        name: "RssAnon",
+        total: None,
+        flag: "--rss-breakdown",
+        unit: "kB",
+        description: "Resident anonymous memory",
+    },
+    Column {
+        name: "RssFile",
+        total: None,
+        flag: "--rss-breakdown",
+        unit: "kB",
+        description: "Resident file mappings",
+    },
+    Column {
+        name: "RssShm",
+        total: None,
+        flag: "--rss-breakdown",
+        unit: "kB",
+        description: "Resident shared memory",
+    },
+    Column {
+        name: "dVSZ",
+        total: None,
+        flag: "--mem-delta",
+        unit: "kB",
+        description: "Change of VSZ since the previous sample",
+    },
+    Column {
+        name: "dRSS",
+        total: None,
+        flag: "--mem-delta",
+        unit: "kB",
+        description: "Change of RSS since the previous sample",
+    },
+    Column {
+        name: "%MEM",
+        total: None,
+        flag: "-r",
+        unit: "%",
+        description: "Share of the physical memory",
+    },
+    Column {
+        name: "StkSize",
+        total: None,
+        flag: "-s",
+        unit: "kB",
+        description: "Memory reserved as stack",
+    },
+    Column {
+        name: "StkRef",
+        total: None,
+        flag: "-s",
+        unit: "kB",
+        description: "Memory used as stack",
+    },
+    Column {
+        name: "kB_rd/s",
+        total: Some("kB_rd"),
+        flag: "-d",
+        unit: "kB/s",
+        description: "Read from disk",
+    },
+    Column {
+        name: "kB_wr/s",
+        total: Some("kB_wr"),
+        flag: "-d",
+        unit: "kB/s",
+        description: "Written, or to be written, to disk",
+    },
+    Column {
+        name: "kB_ccwr/s",
+        total: Some("kB_ccwr"),
+        flag: "-d",
+        unit: "kB/s",
+        description: "Writes to disk cancelled by truncating dirty page cache",
+    },
+    Column {
+        name: "rops/s",
+        total: Some("rops"),
+        flag: "--io-ops",
+        unit: "calls/s",
+        description: "Read system calls, whether or not they reached the disk",
+    },
+    Column {
+        name: "wops/s",
+        total: Some("wops"),
+        flag: "--io-ops",
+        unit: "calls/s",
+        description: "Write system calls, whether or not they reached the disk",
+    },
+    Column {
+        name: "iodelay",
+        total: None,
+        flag: "-d",
+        unit: "clock ticks",
+        description: "Delay waiting for block I/O and swap-in",
+    },
+    Column {
+        name: "cswch/s",
+        total: Some("cswch"),
+        flag: "-w",
+        unit: "switches/s",
+        description: "Voluntary context switches, when blocking on a resource",
+    },
+    Column {
+        name: "nvcswch/s",
+        total: Some("nvcswch"),
+        flag: "-w",
+        unit: "switches/s",
+        description: "Involuntary context switches, when the time slice ran out",
+    },
+    Column {
+        name: "Blocked",
+        total: None,
+        flag: "--signals",
+        unit: "mask",
+        description: "Signals blocked",
+    },
+    Column {
+        name: "Ignored",
+        total: None,
+        flag: "--signals",
+        unit: "mask",
+        description: "Signals ignored",
+    },
+    Column {
+        name: "Caught",
+        total: None,
+        flag: "--signals",
+        unit: "mask",
+        description: "Signals with handlers installed",
+    },
+    Column {
+        name: "oom_score",
+        total: None,
+        flag: "--oom",
+        unit: "",
+        description: "The badness the OOM killer picks its victim by",
+    },
+    Column {
+        name: "oom_adj",
+        total: None,
+        flag: "--oom",
+        unit: "",
+        description: "The adjustment added to the badness, from -1000 to 1000",
+    },
+    Column {
+        name: "START",
+        total: None,
+        flag: "--start-time",
+        unit: "UTC",
+        description: "When the task started",
+    },
+    Column {
+        name: "StartStack",
+        total: None,
+        flag: "--addresses",
+        unit: "address",
+        description: "The bottom of the stack",
+    },
+    Column {
+        name: "KStkESP",
+        total: None,
+        flag: "--addresses",
+        unit: "address",
+        description: "The current stack pointer",
+    },
+    Column {
+        name: "KStkEIP",
+        total: None,
+        flag: "--addresses",
+        unit: "address",
+        description: "The current instruction pointer",
+    },
+    Column {
+        name: "WChan",
+        total: None,
+        flag: "--addresses",
+        unit: "address",
+        description: "The kernel function the task is waiting in",
+    },
+    Column {
+        name: "FDs",
+        total: None,
+        flag: "--sockets",
+        unit: "",
+        description: "Open file descriptors",
+    },
+    Column {
+        name: "Sockets",
+        total: None,
+        flag: "--sockets",
+        unit: "",
+        description: "Open file descriptors that are sockets",
+    },
+    Column {
+        name: "p50%wait",
+        total: None,
+        flag: "--summary",
+        unit: "%",
+        description: "The median %wait of the intervals of the run",
+    },
+    Column {
+        name: "p95%wait",
+        total: None,
+        flag: "--summary",
+        unit: "%",
+        description: "The 95th percentile of the %wait of the intervals of the run",
+    },
+    Column {
+        name: "p99%wait",
+        total: None,
+        flag: "--summary",
+        unit: "%",
+        description: "The 99th percentile of the %wait of the intervals of the run",
+    },
+    Column {
+        name: "Metric",
+        total: None,
+        flag: "--compare-baseline",
+        unit: "",
+        description: "The metric compared against the baseline",
+    },
+    Column {
+        name: "Current",
+        total: None,
+        flag: "--compare-baseline",
+        unit: "",
+        description: "The metric over the interval",
+    },
+    Column {
+        name: "Baseline",
+        total: None,
+        flag: "--compare-baseline",
+        unit: "",
+        description: "The metric saved by --save-baseline",
+    },
+    Column {
+        name: "Delta",
+        total: None,
+        flag: "--compare-baseline",
+        unit: "",
+        description: "The metric over the interval less the baseline",
+    },
+    Column {
+        name: "Ratio",
+        total: None,
+        flag: "--compare-baseline",
+        unit: "",
+        description: "The metric over the interval divided by the baseline",
+    },
+    Column {
+        name: "Command",
+        total: None,
+        flag: "",
+        unit: "",
+        description: "The command name of the task",
+    },
+];
+
+/// The column of the registry with either form of the name `name`
+pub fn column(name: &str) -> Option<&'static Column> {
+    COLUMNS
+        .iter()
+        .find(|column| column.name == name || column.total == Some(name))
+}
+
+/// The headers of the columns of the registry named `names`, in their form for `change`
+pub fn headers(names: &[&'static str], change: ChangeMode) -> Vec<&'static str> {
+    names
+        .iter()
+        .map(|&name| match (change, column(name)) {
+            (
+                ChangeMode::Total,
+                Some(Column {
+                    total: Some(total), ..
+                }),
+            ) => total,
+            _ => name,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnsDisplay<'a> {
+    pub columns: &'a [Column],
+}
+impl<'a> fmt::Display for ColumnsDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<10} {:<26} {:<11} Description",
+            "Column", "Option", "Unit"
+        )?;
+        for column in self.columns {
+            writeln!(
+                f,
+                "{:<10} {:<26} {:<11} {}",
+                column.name, column.flag, column.unit, column.description
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The headers of columns of values right-aligned in `width` after a space
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnHeaderDisplay<'a> {
+    pub names: &'a [&'a str],
+    pub width: usize,
+}
+impl<'a> fmt::Display for ColumnHeaderDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in self.names {
+            start_column(f)?;
+            write!(f, " {name:>width$}", width = self.width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use common::value::{strip_colors, COLUMN_START};
+
+    use crate::{
+        addr::AddressStatsHeaderDisplay,
+        baseline::BaselineComparisonHeaderDisplay,
+        cpu::{CpuStats, CpuStatsHeaderDisplay},
+        ctx_switch::CtxSwitchStatsHeaderDisplay,
+        fd::FdStatsHeaderDisplay,
+        io::IoStatsHeaderDisplay,
+        mem::MemStatsHeaderDisplay,
+        oom::OomStatsHeaderDisplay,
+        process::{ProcessId, TidDisplayOption},
+        read::ProcId,
+        signal::SignalStatsHeaderDisplay,
+        stack::StackStatsHeaderDisplay,
+        start::StartHeaderDisplay,
+        summary::WaitSummary,
+    };
+
+    use super::*;
+
+    /// A summary of the wait of one process over one interval, which prints its header
+    fn wait_summary() -> WaitSummary {
+        let time = Instant::now();
+        let cpu = |time| CpuStats {
+            user_time: 0,
+            system_time: 0,
+            guest_time: 0,
+            wait_time: 0,
+            blkio_delay: 0,
+            system_busy_time: None,
+            sched_time: None,
+            time,
+            processor: None,
+            clock_ticks_per_second: 100,
+        };
+        let id = ProcessId {
+            uid: 0,
+            proc_id: ProcId { pid: 1, tid: None },
+            command: "init".to_string(),
+            state: 'S',
+            start: None,
+        };
+        let mut summary = WaitSummary::new();
+        summary.add(&id, &cpu(time), &cpu(time + Duration::from_secs(1)));
+        summary
+    }
+
+    #[test]
+    fn every_header_is_in_the_registry() {
+        let mut headers = vec![format!("{:#}", wait_summary())];
+        for tid in [TidDisplayOption::Tid, TidDisplayOption::Pid] {
+            for change in [ChangeMode::PerSecond, ChangeMode::Total] {
+                let displays: [&dyn fmt::Display; 4] = [
+                    &CpuStatsHeaderDisplay {
+                        tid,
+                        change,
+                        system_relative: true,
+                        reconcile: true,
+                    },
+                    &MemStatsHeaderDisplay {
+                        tid,
+                        change,
+                        delta: true,
+                        children: true,
+                        breakdown: true,
+                    },
+                    &IoStatsHeaderDisplay {
+                        tid,
+                        change,
+                        ops: true,
+                    },
+                    &CtxSwitchStatsHeaderDisplay { tid, change },
+                ];
+                headers.extend(displays.map(|display| format!("{display:#}")));
+            }
+            let displays: [&dyn fmt::Display; 7] = [
+                &StackStatsHeaderDisplay { tid },
+                &SignalStatsHeaderDisplay { tid },
+                &OomStatsHeaderDisplay { tid },
+                &StartHeaderDisplay { tid },
+                &AddressStatsHeaderDisplay { tid },
+                &FdStatsHeaderDisplay { tid },
+                &BaselineComparisonHeaderDisplay { tid },
+            ];
+            headers.extend(displays.map(|display| format!("{display:#}")));
+        }
+        for header in &headers {
+            let line = header.lines().find(|line| line.contains(COLUMN_START));
+            let line = strip_colors(line.unwrap());
+            for name in line.split(COLUMN_START).skip(1).map(str::trim) {
+                assert!(column(name).is_some(), "{name} of {line:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn headers_of_totals() {
+        let names = ["%usr", "VSZ", "cswch/s"];
+        assert_eq!(headers(&names, ChangeMode::PerSecond), names);
+        assert_eq!(
+            headers(&names, ChangeMode::Total),
+            ["usr(s)", "VSZ", "cswch"]
+        );
+    }
+}
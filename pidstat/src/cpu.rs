@@ -2,16 +2,25 @@ use core::fmt;
 use std::time::Instant;
 
 use common::{
-    change_per_second,
-    value::{item_name_color, normal_color, PercentageColorStatsDisplay, PercentageDisplayLimit},
+    value::{
+        item_name_color, normal_color, start_column, write_columns, zero_int_stat_color,
+        FloatColorStatsDisplay, FloatDisplayLimit, FloatDisplayPostfix, NumberAlign, NumberFormat,
+        PercentageColorStatsDisplay, PercentageDisplayLimit,
+    },
+    ChangeMode,
 };
-use strict_num::PositiveF64;
+use serde::{Deserialize, Serialize};
+use strict_num::{FiniteF64, PositiveF64};
 
-use crate::process::{
-    CommandDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId, TidDisplayOption,
+use crate::{
+    columns::{headers, ColumnHeaderDisplay},
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CpuStats {
     /// In ticks
     ///
@@ -23,6 +32,13 @@ pub struct CpuStats {
     pub guest_time: u64,
     /// In ticks
     pub wait_time: u64,
+    /// Time blocked on block I/O, including swap-in, in ticks
+    pub blkio_delay: u64,
+    /// The time all CPUs of the system spent doing anything but idling, in ticks
+    pub system_busy_time: Option<u64>,
+    /// The time the scheduler ran the task by schedstat, in ticks
+    pub sched_time: Option<u64>,
+    #[serde(skip, default = "Instant::now")]
     pub time: Instant,
     pub processor: Option<u32>,
     pub clock_ticks_per_second: u64,
@@ -31,11 +47,54 @@ pub struct CpuStats {
 #[derive(Debug, Clone)]
 pub struct CpuStatsHeaderDisplay {
     pub tid: TidDisplayOption,
+    pub change: ChangeMode,
+    /// Whether the share of the system's CPU time is reported
+    pub system_relative: bool,
+    /// Whether the accounted CPU time is reported next to the scheduler's
+    pub reconcile: bool,
 }
 impl fmt::Display for CpuStatsHeaderDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", IdHeaderDisplay { tid: self.tid })?;
-        writeln!(f, "    %usr %system  %guest   %wait    %CPU   CPU  Command")?;
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        let names = headers(
+            &["%usr", "%system", "%guest", "%wait", "%blkio", "%CPU"],
+            self.change,
+        );
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &names,
+                width: 7,
+            },
+        )?;
+        if self.system_relative {
+            write_columns(
+                f,
+                &ColumnHeaderDisplay {
+                    names: &["%share"],
+                    width: 7,
+                },
+            )?;
+        }
+        if self.reconcile {
+            let names = headers(&["%acct", "%sched"], self.change);
+            write_columns(
+                f,
+                &ColumnHeaderDisplay {
+                    names: &names,
+                    width: 7,
+                },
+            )?;
+        }
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["CPU"],
+                width: 5,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
         Ok(())
     }
 }
@@ -43,102 +102,287 @@ impl fmt::Display for CpuStatsHeaderDisplay {
 #[derive(Debug, Clone)]
 pub struct CpuStatsValueDisplay<'a> {
     pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
     pub id: &'a ProcessId,
     pub prev_stats: &'a CpuStats,
     pub curr_stats: &'a CpuStats,
+    pub change: ChangeMode,
+    pub number_format: NumberFormat,
+    pub number_align: NumberAlign,
+    pub decimals: usize,
 }
 impl<'a> fmt::Display for CpuStatsValueDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let display = IdValueDisplay {
             process: self.id,
             tid: self.tid,
+            pid_color: self.pid_color,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
         let interval = self.curr_stats.time - self.prev_stats.time;
         let clock_ticks_per_second = self.curr_stats.clock_ticks_per_second;
-
-        let usr = change_per_second(
-            self.prev_stats.user_time.into(),
-            self.curr_stats.user_time.into(),
-            interval,
-        )
-        .unwrap()
-        .get()
-            / clock_ticks_per_second as f64;
-        let usr = PositiveF64::new(usr).unwrap();
-
-        let system = change_per_second(
-            self.prev_stats.system_time.into(),
-            self.curr_stats.system_time.into(),
-            interval,
-        )
-        .unwrap()
-        .get()
-            / clock_ticks_per_second as f64;
-        let system = PositiveF64::new(system).unwrap();
-
-        let guest = change_per_second(
-            self.prev_stats.guest_time.into(),
-            self.curr_stats.guest_time.into(),
-            interval,
-        )
-        .unwrap()
-        .get()
-            / clock_ticks_per_second as f64;
-        let guest = PositiveF64::new(guest).unwrap();
-
-        let wait = change_per_second(
-            self.prev_stats.wait_time.into(),
-            self.curr_stats.wait_time.into(),
-            interval,
-        )
-        .unwrap()
-        .get()
-            / clock_ticks_per_second as f64;
-        let wait = PositiveF64::new(wait).unwrap();
-
-        let cpu = change_per_second(
-            (self.prev_stats.user_time + self.prev_stats.system_time + self.prev_stats.wait_time)
-                .into(),
-            (self.curr_stats.user_time + self.curr_stats.system_time + self.curr_stats.wait_time)
-                .into(),
-            interval,
-        )
-        .unwrap()
-        .get()
-            / clock_ticks_per_second as f64;
-        let cpu = PositiveF64::new(cpu).unwrap();
-
-        let display = PercentageColorStatsDisplay {
-            values: &[usr, system, guest, wait, cpu],
-            width: 7,
-            decimals: 2,
-            limit: PercentageDisplayLimit::ExtremeHigh,
+        // In seconds per second (or in seconds for `ChangeMode::Total`)
+        let time = |prev: u64, curr: u64| {
+            self.change
+                .change(prev.into(), curr.into(), interval)
+                .unwrap()
+                .get()
+                / clock_ticks_per_second as f64
         };
-        write!(f, "{}", display)?;
 
-        if let Some(processor) = self.curr_stats.processor {
-            write!(
-                f,
-                "{start}   {value:3}{end}",
-                start = item_name_color(),
-                value = processor,
-                end = normal_color()
-            )?;
-        } else {
-            write!(
-                f,
-                "{start}   {value:3}{end}",
-                start = item_name_color(),
-                value = '-',
-                end = normal_color()
-            )?;
+        let usr = time(self.prev_stats.user_time, self.curr_stats.user_time);
+        let system = time(self.prev_stats.system_time, self.curr_stats.system_time);
+        let guest = time(self.prev_stats.guest_time, self.curr_stats.guest_time);
+        let wait = time(self.prev_stats.wait_time, self.curr_stats.wait_time);
+        let blkio = time(self.prev_stats.blkio_delay, self.curr_stats.blkio_delay);
+        let cpu = time(
+            self.prev_stats.user_time + self.prev_stats.system_time + self.prev_stats.wait_time,
+            self.curr_stats.user_time + self.curr_stats.system_time + self.curr_stats.wait_time,
+        );
+
+        match self.change {
+            ChangeMode::PerSecond => {
+                let values =
+                    [usr, system, guest, wait, blkio, cpu].map(|v| PositiveF64::new(v).unwrap());
+                let display = PercentageColorStatsDisplay {
+                    values: &values,
+                    width: 7,
+                    decimals: self.decimals,
+                    limit: PercentageDisplayLimit::ExtremeHigh,
+                    format: self.number_format,
+                    align: self.number_align,
+                };
+                write_columns(f, &display)?;
+            }
+            ChangeMode::Total => {
+                let values =
+                    [usr, system, guest, wait, blkio, cpu].map(|v| FiniteF64::new(v).unwrap());
+                let display = FloatColorStatsDisplay {
+                    values: &values,
+                    width: 7,
+                    postfix: FloatDisplayPostfix::Decimals(self.decimals),
+                    limit: FloatDisplayLimit::ExtremeLow,
+                    format: self.number_format,
+                    align: self.number_align,
+                };
+                write_columns(f, &display)?;
+            }
+        }
+
+        if let (Some(prev), Some(curr)) = (
+            self.prev_stats.system_busy_time,
+            self.curr_stats.system_busy_time,
+        ) {
+            // Guest time is part of the user time of `/proc/stat`
+            let ticks = |s: &CpuStats| s.user_time + s.guest_time + s.system_time;
+            let task = ticks(self.curr_stats).checked_sub(ticks(self.prev_stats));
+            match (task, curr.checked_sub(prev)) {
+                (Some(task), Some(system)) => {
+                    let share = match system {
+                        0 => 0.,
+                        system => task as f64 / system as f64,
+                    };
+                    let display = PercentageColorStatsDisplay {
+                        values: &[PositiveF64::new(share).unwrap()],
+                        width: 7,
+                        decimals: self.decimals,
+                        limit: PercentageDisplayLimit::ExtremeHigh,
+                        format: self.number_format,
+                        align: self.number_align,
+                    };
+                    write_columns(f, &display)?;
+                }
+                // A counter went backwards, as when the PID was reused
+                _ => {
+                    start_column(f)?;
+                    write!(
+                        f,
+                        "{start} {dash:>7}{end}",
+                        start = zero_int_stat_color(),
+                        dash = "-",
+                        end = normal_color()
+                    )?
+                }
+            }
         }
 
-        let display = CommandDisplay { process: self.id };
-        writeln!(f, "{}", display)?;
+        if let (Some(prev), Some(curr)) = (self.prev_stats.sched_time, self.curr_stats.sched_time) {
+            let ticks = |s: &CpuStats| s.user_time + s.guest_time + s.system_time;
+            let acct = time(ticks(self.prev_stats), ticks(self.curr_stats));
+            let sched = time(prev, curr);
+            match self.change {
+                ChangeMode::PerSecond => {
+                    let values = [acct, sched].map(|v| PositiveF64::new(v).unwrap());
+                    let display = PercentageColorStatsDisplay {
+                        values: &values,
+                        width: 7,
+                        decimals: self.decimals,
+                        limit: PercentageDisplayLimit::ExtremeHigh,
+                        format: self.number_format,
+                        align: self.number_align,
+                    };
+                    write_columns(f, &display)?;
+                }
+                ChangeMode::Total => {
+                    let values = [acct, sched].map(|v| FiniteF64::new(v).unwrap());
+                    let display = FloatColorStatsDisplay {
+                        values: &values,
+                        width: 7,
+                        postfix: FloatDisplayPostfix::Decimals(self.decimals),
+                        limit: FloatDisplayLimit::ExtremeLow,
+                        format: self.number_format,
+                        align: self.number_align,
+                    };
+                    write_columns(f, &display)?;
+                }
+            }
+        }
+
+        // Right-aligned like the numbers, with room for the core numbers of large machines
+        let processor = match self.curr_stats.processor {
+            Some(processor) => processor.to_string(),
+            None => "-".to_string(),
+        };
+        start_column(f)?;
+        write!(
+            f,
+            "{start}  {value:>4}{end}",
+            start = item_name_color(),
+            value = processor,
+            end = normal_color()
+        )?;
+
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use common::value::strip_colors;
+
+    use crate::read::ProcId;
+
+    use super::*;
+
+    fn cpu_stats(time: Instant, processor: Option<u32>) -> CpuStats {
+        CpuStats {
+            user_time: 0,
+            system_time: 0,
+            guest_time: 0,
+            wait_time: 0,
+            blkio_delay: 0,
+            system_busy_time: None,
+            sched_time: None,
+            time,
+            processor,
+            clock_ticks_per_second: 100,
+        }
+    }
+
+    #[test]
+    fn processor_column_of_many_cores() {
+        let header = CpuStatsHeaderDisplay {
+            tid: TidDisplayOption::Pid,
+            change: ChangeMode::PerSecond,
+            system_relative: false,
+            reconcile: false,
+        };
+        let header = strip_colors(&header.to_string());
+        let header_end = header.find("CPU  Command").unwrap() + "CPU".len();
+        let id = ProcessId {
+            uid: 0,
+            proc_id: ProcId { pid: 1, tid: None },
+            command: "init".to_string(),
+            state: 'S',
+            start: None,
+        };
+        let time = Instant::now();
+        let cases = [
+            (Some(7), "7"),
+            (Some(127), "127"),
+            (Some(1023), "1023"),
+            (None, "-"),
+        ];
+        for (processor, text) in cases {
+            let prev_stats = cpu_stats(time, processor);
+            let curr_stats = cpu_stats(time + Duration::from_secs(1), processor);
+            let value = CpuStatsValueDisplay {
+                tid: TidDisplayOption::Pid,
+                pid_color: false,
+                id: &id,
+                prev_stats: &prev_stats,
+                curr_stats: &curr_stats,
+                change: ChangeMode::PerSecond,
+                number_format: NumberFormat::Fixed,
+                number_align: NumberAlign::Right,
+                decimals: 2,
+            };
+            let value = strip_colors(&value.to_string());
+            let value_end = value.find(&format!("{text}  init")).unwrap() + text.len();
+            assert_eq!(value_end, header_end, "{value}");
+            assert_eq!(value.find("init"), header.find("Command"), "{value}");
+        }
+    }
+
+    #[test]
+    fn share_of_counters_that_went_backwards() {
+        let header = CpuStatsHeaderDisplay {
+            tid: TidDisplayOption::Pid,
+            change: ChangeMode::PerSecond,
+            system_relative: true,
+            reconcile: false,
+        };
+        let header = strip_colors(&header.to_string());
+        let id = ProcessId {
+            uid: 0,
+            proc_id: ProcId { pid: 1, tid: None },
+            command: "init".to_string(),
+            state: 'S',
+            start: None,
+        };
+        let time = Instant::now();
+        let prev_stats = CpuStats {
+            user_time: 50,
+            system_busy_time: Some(1000),
+            ..cpu_stats(time, Some(0))
+        };
+        let cases = [
+            (Some(1100), 100, "50.00"),
+            // The busy time of the system went backwards
+            (Some(900), 100, "-"),
+        ];
+        for (system_busy_time, user_time, text) in cases {
+            let curr_stats = CpuStats {
+                user_time,
+                system_busy_time,
+                ..cpu_stats(time + Duration::from_secs(1), Some(0))
+            };
+            let value = CpuStatsValueDisplay {
+                tid: TidDisplayOption::Pid,
+                pid_color: false,
+                id: &id,
+                prev_stats: &prev_stats,
+                curr_stats: &curr_stats,
+                change: ChangeMode::PerSecond,
+                number_format: NumberFormat::Fixed,
+                number_align: NumberAlign::Right,
+                decimals: 2,
+            };
+            let value = strip_colors(&value.to_string());
+            let header_end = header.find("%share").unwrap() + "%share".len();
+            let value_end = header_end - text.len();
+            assert_eq!(&value[value_end..header_end], text, "{value}");
+        }
+    }
+}
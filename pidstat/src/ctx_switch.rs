@@ -2,31 +2,50 @@ use core::fmt;
 use std::time::Instant;
 
 use common::{
-    change_per_second,
-    value::{FloatColorStatsDisplay, FloatDisplayPostfix},
+    value::{
+        write_columns, FloatColorStatsDisplay, FloatDisplayLimit, FloatDisplayPostfix, NumberAlign,
+        NumberFormat,
+    },
+    ChangeMode,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::process::{
-    CommandDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId, TidDisplayOption,
+use crate::{
+    columns::{headers, ColumnHeaderDisplay},
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CtxSwitchStats {
     /// voluntary_ctxt_switches
     pub nvcsw: u64,
     /// nonvoluntary_ctxt_switches
     pub nivcsw: u64,
+    #[serde(skip, default = "Instant::now")]
     pub time: Instant,
 }
 
 #[derive(Debug, Clone)]
 pub struct CtxSwitchStatsHeaderDisplay {
     pub tid: TidDisplayOption,
+    pub change: ChangeMode,
 }
 impl fmt::Display for CtxSwitchStatsHeaderDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", IdHeaderDisplay { tid: self.tid })?;
-        writeln!(f, "   cswch/s nvcswch/s  Command")?;
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        let names = headers(&["cswch/s", "nvcswch/s"], self.change);
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &names,
+                width: 9,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
         Ok(())
     }
 }
@@ -34,43 +53,101 @@ impl fmt::Display for CtxSwitchStatsHeaderDisplay {
 #[derive(Debug, Clone)]
 pub struct CtxSwitchStatsValueDisplay<'a> {
     pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
     pub id: &'a ProcessId,
     pub prev_stats: &'a CtxSwitchStats,
     pub curr_stats: &'a CtxSwitchStats,
+    pub change: ChangeMode,
+    pub number_format: NumberFormat,
+    pub number_align: NumberAlign,
+    /// The decimals of the rates
+    pub decimals: usize,
+    /// Whether to highlight the involuntary switches as an outlier among the task's siblings
+    pub outlier: bool,
 }
 impl<'a> fmt::Display for CtxSwitchStatsValueDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let display = IdValueDisplay {
             process: self.id,
             tid: self.tid,
+            pid_color: self.pid_color,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
         let interval = self.curr_stats.time - self.prev_stats.time;
+        // Totals are counts
+        let decimals = match self.change {
+            ChangeMode::PerSecond => self.decimals,
+            ChangeMode::Total => 0,
+        };
 
-        let nvcsw = change_per_second(
-            self.prev_stats.nvcsw.into(),
-            self.curr_stats.nvcsw.into(),
-            interval,
-        )
-        .expect("nvcsw");
-        let nivcsw = change_per_second(
-            self.prev_stats.nivcsw.into(),
-            self.curr_stats.nivcsw.into(),
-            interval,
-        )
-        .expect("nivcsw");
+        let nvcsw = self
+            .change
+            .change(
+                self.prev_stats.nvcsw.into(),
+                self.curr_stats.nvcsw.into(),
+                interval,
+            )
+            .expect("nvcsw");
+        let nivcsw = self
+            .change
+            .change(
+                self.prev_stats.nivcsw.into(),
+                self.curr_stats.nivcsw.into(),
+                interval,
+            )
+            .expect("nivcsw");
 
         let display = FloatColorStatsDisplay {
-            values: &[nvcsw, nivcsw],
+            values: &[nvcsw],
             width: 9,
-            postfix: FloatDisplayPostfix::Decimals(2),
+            postfix: FloatDisplayPostfix::Decimals(decimals),
+            limit: FloatDisplayLimit::ExtremeLow,
+            format: self.number_format,
+            align: self.number_align,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
+        let limit = match self.outlier {
+            true => FloatDisplayLimit::ExtremeHigh { extreme: 0.0 },
+            false => FloatDisplayLimit::ExtremeLow,
+        };
+        let display = FloatColorStatsDisplay {
+            values: &[nivcsw],
+            width: 9,
+            postfix: FloatDisplayPostfix::Decimals(decimals),
+            limit,
+            format: self.number_format,
+            align: self.number_align,
+        };
+        write_columns(f, &display)?;
 
-        let display = CommandDisplay { process: self.id };
-        writeln!(f, "{}", display)?;
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
 
         Ok(())
     }
 }
+
+/// The involuntary switches per second of the task between the two samples
+pub fn nivcsw_rate(prev: &CtxSwitchStats, curr: &CtxSwitchStats) -> f64 {
+    let interval = (curr.time - prev.time).as_secs_f64();
+    curr.nivcsw.saturating_sub(prev.nivcsw) as f64 / interval
+}
+
+/// The rate above which one of `rates` is an outlier: two standard deviations above their mean.
+///
+/// `None` for fewer than two rates.
+pub fn outlier_threshold(rates: &[f64]) -> Option<f64> {
+    if rates.len() < 2 {
+        return None;
+    }
+    let n = rates.len() as f64;
+    let mean = rates.iter().sum::<f64>() / n;
+    let variance = rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+    Some(mean + 2. * variance.sqrt())
+}
@@ -0,0 +1,80 @@
+use core::fmt;
+use std::time::Instant;
+
+use common::value::{
+    int_stat_color, normal_color, start_column, write_columns, zero_int_stat_color,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    columns::ColumnHeaderDisplay,
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FdStats {
+    /// The open file descriptors
+    pub fds: u64,
+    /// The open file descriptors that are sockets
+    pub sockets: u64,
+    #[serde(skip, default = "Instant::now")]
+    pub time: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct FdStatsHeaderDisplay {
+    pub tid: TidDisplayOption,
+}
+impl fmt::Display for FdStatsHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        let names = ColumnHeaderDisplay {
+            names: &["FDs", "Sockets"],
+            width: 9,
+        };
+        write_columns(f, &names)?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FdStatsValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub curr_stats: &'a FdStats,
+}
+impl<'a> fmt::Display for FdStatsValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+
+        for value in [self.curr_stats.fds, self.curr_stats.sockets] {
+            let start = match value {
+                0 => zero_int_stat_color(),
+                _ => int_stat_color(),
+            };
+            start_column(f)?;
+            write!(f, "{start} {value:9}{end}", end = normal_color())?;
+        }
+
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::{self, Write};
+
+use thiserror::Error;
+
+use crate::record::{EventRecord, SampleRecord};
+
+/// Write `records` as lines of the Graphite plaintext protocol:
+/// `pidstat.<command>.<pid>[.<tid>].<metric> <value> <timestamp>`
+pub fn write_graphite(sink: &mut impl Write, records: &[SampleRecord]) -> fmt::Result {
+    for record in records {
+        let mut path = format!(
+            "pidstat.{command}.{pid}",
+            command = graphite_node(&record.command),
+            pid = record.pid
+        );
+        if let Some(tid) = record.tid {
+            write!(path, ".{tid}")?;
+        }
+        let timestamp = record.timestamp as u64;
+        for (metric, value) in graphite_metrics(record) {
+            writeln!(sink, "{path}.{metric} {value} {timestamp}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace everything but ASCII alphanumerics, `-` and `_` so that `node` stays a single node of a metric path
+fn graphite_node(node: &str) -> String {
+    node.chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect()
+}
+
+fn graphite_metrics(record: &SampleRecord) -> Vec<(&'static str, f64)> {
+    let mut metrics = vec![];
+    if let Some(cpu) = &record.cpu {
+        metrics.extend([
+            ("usr_pct", cpu.usr),
+            ("system_pct", cpu.system),
+            ("guest_pct", cpu.guest),
+            ("wait_pct", cpu.wait),
+            ("blkio_pct", cpu.blkio),
+            ("cpu_pct", cpu.cpu),
+        ]);
+    }
+    if let Some(mem) = &record.mem {
+        metrics.extend([
+            ("minflt", mem.minflt),
+            ("majflt", mem.majflt),
+            ("vsz_kb", mem.vsz as f64),
+            ("rss_kb", mem.rss as f64),
+            ("mem_pct", mem.mem),
+        ]);
+    }
+    if let Some(stack) = &record.stack {
+        metrics.extend([
+            ("stk_size_kb", stack.stk_size as f64),
+            ("stk_ref_kb", stack.stk_ref as f64),
+        ]);
+    }
+    if let Some(io) = &record.io {
+        metrics.extend([
+            ("kb_rd", io.kb_rd),
+            ("kb_wr", io.kb_wr),
+            ("kb_ccwr", io.kb_ccwr),
+            ("iodelay", io.iodelay as f64),
+        ]);
+    }
+    if let Some(ctx_switch) = &record.ctx_switch {
+        metrics.extend([("cswch", ctx_switch.cswch), ("nvcswch", ctx_switch.nvcswch)]);
+    }
+    if let Some(oom) = &record.oom {
+        metrics.extend([
+            ("oom_score", oom.score as f64),
+            ("oom_score_adj", oom.score_adj as f64),
+        ]);
+    }
+    if let Some(fd) = &record.fd {
+        metrics.extend([("fds", fd.fds as f64), ("sockets", fd.sockets as f64)]);
+    }
+    metrics
+}
+
+/// Write `records` as MessagePack, each prefixed by its length as a big-endian `u32`
+#[cfg(feature = "msgpack")]
+pub fn write_msgpack(
+    sink: &mut Vec<u8>,
+    records: &[SampleRecord],
+) -> Result<(), rmp_serde::encode::Error> {
+    for record in records {
+        let bytes = rmp_serde::to_vec_named(record)?;
+        let len = u32::try_from(bytes.len()).expect("record length");
+        sink.extend_from_slice(&len.to_be_bytes());
+        sink.extend_from_slice(&bytes);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Malformed JSON on line {line}: {source}")]
+    Json {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[cfg(feature = "msgpack")]
+    #[error("Malformed MessagePack: {0}")]
+    Msgpack(#[from] rmp_serde::decode::Error),
+    #[error("Truncated MessagePack record")]
+    Truncated,
+    #[error("A MessagePack capture needs pidstat built with the `msgpack` feature")]
+    Unsupported,
+}
+
+/// Read the samples of a capture, which is NDJSON if it starts with `{` and length-prefixed
+/// MessagePack otherwise.
+///
+/// The error records of NDJSON are skipped.
+pub fn read_capture(bytes: &[u8]) -> Result<Vec<SampleRecord>, CaptureError> {
+    let mut records = vec![];
+    if bytes.first().is_none_or(|b| *b == b'{') {
+        let lines = bytes.split(|b| *b == b'\n').enumerate();
+        for (i, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let error = match serde_json::from_slice::<SampleRecord>(line) {
+                Ok(record) => {
+                    records.push(record);
+                    continue;
+                }
+                Err(e) => e,
+            };
+            if serde_json::from_slice::<EventRecord>(line).is_err() {
+                return Err(CaptureError::Json {
+                    line: i + 1,
+                    source: error,
+                });
+            }
+        }
+        return Ok(records);
+    }
+    read_msgpack(bytes, &mut records)?;
+    Ok(records)
+}
+
+#[cfg(feature = "msgpack")]
+fn read_msgpack(mut bytes: &[u8], records: &mut Vec<SampleRecord>) -> Result<(), CaptureError> {
+    while !bytes.is_empty() {
+        let (len, rest) = bytes
+            .split_first_chunk::<4>()
+            .ok_or(CaptureError::Truncated)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        let record = rest.get(..len).ok_or(CaptureError::Truncated)?;
+        records.push(rmp_serde::from_slice(record)?);
+        bytes = &rest[len..];
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "msgpack"))]
+fn read_msgpack(_bytes: &[u8], _records: &mut Vec<SampleRecord>) -> Result<(), CaptureError> {
+    Err(CaptureError::Unsupported)
+}
@@ -2,31 +2,75 @@ use core::fmt;
 use std::time::Instant;
 
 use common::{
-    change_per_second,
-    value::{FloatColorStatsDisplay, FloatDisplayPostfix, U64ColorStatsDisplay},
+    value::{
+        write_columns, FloatColorStatsDisplay, FloatDisplayLimit, FloatDisplayPostfix, NumberAlign,
+        NumberFormat, U64ColorStatsDisplay,
+    },
+    ChangeMode,
 };
+use serde::{Deserialize, Serialize};
+use strict_num::FiniteF64;
 
-use crate::process::{
-    CommandDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId, TidDisplayOption,
+use crate::{
+    columns::{headers, ColumnHeaderDisplay},
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IoStats {
     pub read_bytes: u64,
     pub write_bytes: u64,
     pub cancelled_write_bytes: u64,
     pub blkio_swapin_delays: u64,
+    /// The number of read system calls
+    #[serde(default)]
+    pub syscr: u64,
+    /// The number of write system calls
+    #[serde(default)]
+    pub syscw: u64,
+    #[serde(skip, default = "Instant::now")]
     pub time: Instant,
 }
 
 #[derive(Debug, Clone)]
 pub struct IoStatsHeaderDisplay {
     pub tid: TidDisplayOption,
+    pub change: ChangeMode,
+    pub ops: bool,
 }
 impl fmt::Display for IoStatsHeaderDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", IdHeaderDisplay { tid: self.tid })?;
-        writeln!(f, "   kB_rd/s   kB_wr/s kB_ccwr/s iodelay  Command")?;
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        let names = headers(&["kB_rd/s", "kB_wr/s", "kB_ccwr/s"], self.change);
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &names,
+                width: 9,
+            },
+        )?;
+        if self.ops {
+            let names = headers(&["rops/s", "wops/s"], self.change);
+            write_columns(
+                f,
+                &ColumnHeaderDisplay {
+                    names: &names,
+                    width: 9,
+                },
+            )?;
+        }
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["iodelay"],
+                width: 7,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
         Ok(())
     }
 }
@@ -34,45 +78,87 @@ impl fmt::Display for IoStatsHeaderDisplay {
 #[derive(Debug, Clone)]
 pub struct IoStatsValueDisplay<'a> {
     pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
     pub id: &'a ProcessId,
     pub prev_stats: &'a IoStats,
     pub curr_stats: &'a IoStats,
+    pub change: ChangeMode,
+    pub number_format: NumberFormat,
+    pub number_align: NumberAlign,
+    /// The decimals of the rates
+    pub decimals: usize,
+    /// Whether to write the rates of read and write system calls
+    pub ops: bool,
 }
 impl<'a> fmt::Display for IoStatsValueDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let display = IdValueDisplay {
             process: self.id,
             tid: self.tid,
+            pid_color: self.pid_color,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
         let interval = self.curr_stats.time - self.prev_stats.time;
+        // Totals are counts
+        let decimals = match self.change {
+            ChangeMode::PerSecond => self.decimals,
+            ChangeMode::Total => 0,
+        };
 
-        let r_bytes = change_per_second(
-            self.prev_stats.read_bytes.into(),
-            self.curr_stats.read_bytes.into(),
-            interval,
-        )
-        .unwrap();
-        let w_bytes = change_per_second(
-            self.prev_stats.write_bytes.into(),
-            self.curr_stats.write_bytes.into(),
-            interval,
-        )
-        .unwrap();
-        let c_bytes = change_per_second(
-            self.prev_stats.cancelled_write_bytes.into(),
-            self.curr_stats.cancelled_write_bytes.into(),
-            interval,
-        )
-        .unwrap();
+        // The counters are in bytes and the columns in kB
+        let kb = |prev: u64, curr: u64| {
+            let bytes = self
+                .change
+                .change(prev.into(), curr.into(), interval)
+                .unwrap();
+            FiniteF64::new(bytes.get() / 1024.).unwrap()
+        };
+        let r_bytes = kb(self.prev_stats.read_bytes, self.curr_stats.read_bytes);
+        let w_bytes = kb(self.prev_stats.write_bytes, self.curr_stats.write_bytes);
+        let c_bytes = kb(
+            self.prev_stats.cancelled_write_bytes,
+            self.curr_stats.cancelled_write_bytes,
+        );
 
         let display = FloatColorStatsDisplay {
             values: &[r_bytes, w_bytes, c_bytes],
             width: 9,
-            postfix: FloatDisplayPostfix::Decimals(2),
+            postfix: FloatDisplayPostfix::Decimals(decimals),
+            limit: FloatDisplayLimit::ExtremeLow,
+            format: self.number_format,
+            align: self.number_align,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
+
+        if self.ops {
+            let rops = self
+                .change
+                .change(
+                    self.prev_stats.syscr.into(),
+                    self.curr_stats.syscr.into(),
+                    interval,
+                )
+                .unwrap();
+            let wops = self
+                .change
+                .change(
+                    self.prev_stats.syscw.into(),
+                    self.curr_stats.syscw.into(),
+                    interval,
+                )
+                .unwrap();
+            let display = FloatColorStatsDisplay {
+                values: &[rops, wops],
+                width: 9,
+                postfix: FloatDisplayPostfix::Decimals(decimals),
+                limit: FloatDisplayLimit::ExtremeLow,
+                format: self.number_format,
+                align: self.number_align,
+            };
+            write_columns(f, &display)?;
+        }
 
         let io_delay = self.curr_stats.blkio_swapin_delays - self.prev_stats.blkio_swapin_delays;
         let display = U64ColorStatsDisplay {
@@ -80,10 +166,14 @@ impl<'a> fmt::Display for IoStatsValueDisplay<'a> {
             width: 7,
             unit: None,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
-        let display = CommandDisplay { process: self.id };
-        writeln!(f, "{}", display)?;
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
 
         Ok(())
     }
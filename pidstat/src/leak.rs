@@ -0,0 +1,59 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    num::NonZeroUsize,
+};
+
+/// Detects processes whose RSS grows on every interval of a sliding window
+#[derive(Debug, Clone)]
+pub struct LeakWatchdog {
+    /// In intervals
+    window: NonZeroUsize,
+    /// Minimum growth over the window in kB
+    threshold: u64,
+    /// RSS samples in kB, oldest first
+    rss: BTreeMap<usize, VecDeque<u64>>,
+}
+impl LeakWatchdog {
+    pub fn new(window: NonZeroUsize, threshold: u64) -> Self {
+        Self {
+            window,
+            threshold,
+            rss: BTreeMap::new(),
+        }
+    }
+
+    /// Record the latest RSS of `pid` in kB.
+    ///
+    /// Return the RSS growth in kB over the window if it grew on every interval by at least the threshold in total.
+    /// The window then starts over so that the same growth is not reported on every interval.
+    pub fn observe(&mut self, pid: usize, rss: u64) -> Option<u64> {
+        let samples = self.rss.entry(pid).or_default();
+        samples.push_back(rss);
+        // A window of `n` intervals spans `n + 1` samples
+        while self.window.get() + 1 < samples.len() {
+            samples.pop_front();
+        }
+        if samples.len() < self.window.get() + 1 {
+            return None;
+        }
+        let monotonic = samples
+            .iter()
+            .zip(samples.iter().skip(1))
+            .all(|(prev, curr)| prev < curr);
+        let growth = samples.back().unwrap() - samples.front().unwrap();
+        if !monotonic || growth < self.threshold {
+            return None;
+        }
+        samples.drain(..samples.len() - 1);
+        Some(growth)
+    }
+
+    pub fn forget(&mut self, pid: usize) {
+        self.rss.remove(&pid);
+    }
+
+    /// Forget every process for which `f` returns `false`
+    pub fn retain(&mut self, mut f: impl FnMut(usize) -> bool) {
+        self.rss.retain(|pid, _| f(*pid));
+    }
+}
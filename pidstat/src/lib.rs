@@ -1,168 +1,949 @@
 use core::fmt;
+use std::collections::{BTreeMap, BTreeSet};
 
+use addr::{AddressStatsHeaderDisplay, AddressStatsValueDisplay};
+use common::{
+    value::{write_columns, NumberAlign, NumberFormat},
+    ChangeMode,
+};
 use cpu::CpuStatsValueDisplay;
-use ctx_switch::{CtxSwitchStatsHeaderDisplay, CtxSwitchStatsValueDisplay};
+use ctx_switch::{
+    nivcsw_rate, outlier_threshold, CtxSwitchStatsHeaderDisplay, CtxSwitchStatsValueDisplay,
+};
+use fd::{FdStatsHeaderDisplay, FdStatsValueDisplay};
 use io::{IoStatsHeaderDisplay, IoStatsValueDisplay};
 use mem::{MemStatsHeaderDisplay, MemStatsValueDisplay};
-use process::TidDisplayOption;
+use oom::{OomStatsHeaderDisplay, OomStatsValueDisplay};
+use process::{
+    CommandChangeDisplay, ComponentStats, RestrictedValueDisplay, ThreadStateSummaryDisplay,
+    TidDisplayOption,
+};
 use read::TaskGroupStats;
+use signal::{SignalStatsHeaderDisplay, SignalStatsValueDisplay};
 use stack::{StackStatsHeaderDisplay, StackStatsValueDisplay};
+use start::{StartHeaderDisplay, StartValueDisplay};
 
 use crate::cpu::CpuStatsHeaderDisplay;
 
+pub mod adaptive;
+pub mod addr;
+pub mod alert;
+pub mod baseline;
+pub mod clock;
+pub mod columns;
 pub mod cpu;
 pub mod ctx_switch;
+pub mod fd;
+pub mod format;
 pub mod io;
+pub mod leak;
 pub mod mem;
+pub mod metadata;
+pub mod oom;
+pub mod output;
 pub mod process;
 pub mod read;
+pub mod record;
+pub mod select;
+pub mod signal;
+pub mod smooth;
+pub mod snapshot;
 pub mod stack;
+pub mod start;
+pub mod summary;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayOptions {
+    pub change: ChangeMode,
+    pub number_format: NumberFormat,
+    pub number_align: NumberAlign,
+    /// Whether to write the column headers before the rows
+    pub header: bool,
+    pub precision: Precision,
+    /// Whether to report the sum of the threads as the process row instead of the process's own
+    /// counters
+    pub merge_threads: bool,
+    /// Whether to report how many threads are in each state instead of a row for every thread
+    pub thread_summary: bool,
+    /// Whether to report the change of VSZ and RSS since the previous sample
+    pub mem_delta: bool,
+    /// Whether to report the fault rates of the children next to those of the task
+    pub children_faults: bool,
+    /// Whether to split RSS into anonymous, file, and shared memory
+    pub rss_breakdown: bool,
+    /// Whether to report the rates of read and write system calls next to the IO rates
+    pub io_ops: bool,
+    /// Whether to highlight the threads whose involuntary switch rate is an outlier among their
+    /// siblings
+    pub highlight_outliers: bool,
+    /// Whether to color the IDs and command of each process by its PID
+    pub color_by_pid: bool,
+    /// Whether to show dashes for the components that could not be read instead of leaving
+    /// their tables out
+    pub blank_components: bool,
+}
+
+/// The decimals of the rates and percentages of each component
+#[derive(Debug, Clone, Copy)]
+pub struct Precision {
+    pub cpu: usize,
+    pub mem: usize,
+    pub io: usize,
+    pub ctx_switch: usize,
+}
+impl Default for Precision {
+    fn default() -> Self {
+        Self {
+            cpu: 2,
+            mem: 2,
+            io: 2,
+            ctx_switch: 2,
+        }
+    }
+}
+
+/// Forget the processes not in `monitored` from each of `maps` so that the state kept per process
+/// does not grow with process churn
+pub fn forget_unmonitored<V>(monitored: &BTreeSet<usize>, maps: &mut [&mut BTreeMap<usize, V>]) {
+    for map in maps {
+        map.retain(|p, _| monitored.contains(p));
+    }
+}
+
+/// Write the report of a thread group between two samples to `sink` instead of stdout
+pub fn render_report(
+    sink: &mut impl fmt::Write,
+    prev_stats: &TaskGroupStats,
+    curr_stats: &TaskGroupStats,
+    options: DisplayOptions,
+) -> fmt::Result {
+    let renames = TaskGroupCommandChangeDisplay {
+        prev_stats,
+        curr_stats,
+        pid_color: options.color_by_pid,
+    };
+    let display = TaskGroupStatsDisplay {
+        prev_stats,
+        curr_stats,
+        options,
+    };
+    write!(sink, "{renames}{display}")
+}
+
+/// Render the report of the change from `prev` to `curr`, which need not have been sampled live,
+/// e.g. those of two [`snapshot::Snapshot`]s
+pub fn render_delta(
+    prev: &TaskGroupStats,
+    curr: &TaskGroupStats,
+    options: DisplayOptions,
+) -> String {
+    let mut report = String::new();
+    render_report(&mut report, prev, curr, options).unwrap();
+    report
+}
 
 pub struct TaskGroupStatsDisplay<'a> {
     pub prev_stats: &'a TaskGroupStats,
     pub curr_stats: &'a TaskGroupStats,
+    pub options: DisplayOptions,
 }
 impl<'a> fmt::Display for TaskGroupStatsDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.options.merge_threads && !self.curr_stats.task.is_empty() {
+            let (prev_stats, curr_stats) = merge_threads(self.prev_stats, self.curr_stats);
+            let display = TaskGroupStatsDisplay {
+                prev_stats: &prev_stats,
+                curr_stats: &curr_stats,
+                options: DisplayOptions {
+                    merge_threads: false,
+                    ..self.options
+                },
+            };
+            return write_columns(f, &display);
+        }
+        if self.options.thread_summary && !self.curr_stats.task.is_empty() {
+            let without_task = |stats: &TaskGroupStats| TaskGroupStats {
+                task: BTreeMap::new(),
+                ..stats.clone()
+            };
+            let display = TaskGroupStatsDisplay {
+                prev_stats: &without_task(self.prev_stats),
+                curr_stats: &without_task(self.curr_stats),
+                options: self.options,
+            };
+            write_columns(f, &display)?;
+            let summary = ThreadStateSummaryDisplay {
+                tid: TidDisplayOption::Pid,
+                pid_color: self.options.color_by_pid,
+                id: &self.curr_stats.process.id,
+                task: &self.curr_stats.task,
+            };
+            return write_columns(f, &summary);
+        }
+
         let tid_display_option = if self.curr_stats.task.is_empty() {
             TidDisplayOption::Pid
         } else {
             TidDisplayOption::Tid
         };
 
-        if self.curr_stats.process.components.cpu.is_some() {
+        if let Some(curr_stats) = &self.curr_stats.process.components.cpu {
             let header = CpuStatsHeaderDisplay {
                 tid: tid_display_option,
+                change: self.options.change,
+                system_relative: curr_stats.system_busy_time.is_some(),
+                reconcile: curr_stats.sched_time.is_some(),
             };
-            write!(f, "{header}")?;
-            let process = CpuStatsValueDisplay {
-                tid: tid_display_option,
-                id: &self.curr_stats.process.id,
-                prev_stats: self.prev_stats.process.components.cpu.as_ref().unwrap(),
-                curr_stats: self.curr_stats.process.components.cpu.as_ref().unwrap(),
-            };
-            write!(f, "{process}")?;
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            // The row of the process is skipped if it has nothing to compare with
+            if let Some(prev_stats) = &self.prev_stats.process.components.cpu {
+                let process = CpuStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &self.curr_stats.process.id,
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.cpu,
+                };
+                write_columns(f, &process)?;
+            }
             for (tid, stats) in &self.curr_stats.task {
                 let Some(prev_stats) = self.prev_stats.task.get(tid) else {
                     continue;
                 };
+                let (Some(prev_stats), Some(curr_stats)) =
+                    (&prev_stats.components.cpu, &stats.components.cpu)
+                else {
+                    continue;
+                };
                 let task = CpuStatsValueDisplay {
                     tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
                     id: &stats.id,
-                    prev_stats: prev_stats.components.cpu.as_ref().unwrap(),
-                    curr_stats: stats.components.cpu.as_ref().unwrap(),
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.cpu,
                 };
-                write!(f, "{task}")?;
+                write_columns(f, &task)?;
             }
         }
-        if self.curr_stats.process.components.mem.is_some() {
+        if let Some(curr_stats) = &self.curr_stats.process.components.mem {
             let header = MemStatsHeaderDisplay {
                 tid: tid_display_option,
+                change: self.options.change,
+                delta: self.options.mem_delta,
+                children: self.options.children_faults,
+                breakdown: self.options.rss_breakdown,
             };
-            write!(f, "{header}")?;
-            let process = MemStatsValueDisplay {
-                tid: tid_display_option,
-                id: &self.curr_stats.process.id,
-                prev_stats: self.prev_stats.process.components.mem.as_ref().unwrap(),
-                curr_stats: self.curr_stats.process.components.mem.as_ref().unwrap(),
-            };
-            write!(f, "{process}")?;
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            if let Some(prev_stats) = &self.prev_stats.process.components.mem {
+                let process = MemStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &self.curr_stats.process.id,
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.mem,
+                    delta: self.options.mem_delta,
+                    children: self.options.children_faults,
+                    breakdown: self.options.rss_breakdown,
+                };
+                write_columns(f, &process)?;
+            }
             for (tid, stats) in &self.curr_stats.task {
                 let Some(prev_stats) = self.prev_stats.task.get(tid) else {
                     continue;
                 };
+                let (Some(prev_stats), Some(curr_stats)) =
+                    (&prev_stats.components.mem, &stats.components.mem)
+                else {
+                    continue;
+                };
                 let task = MemStatsValueDisplay {
                     tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
                     id: &stats.id,
-                    prev_stats: prev_stats.components.mem.as_ref().unwrap(),
-                    curr_stats: stats.components.mem.as_ref().unwrap(),
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.mem,
+                    delta: self.options.mem_delta,
+                    children: self.options.children_faults,
+                    breakdown: self.options.rss_breakdown,
                 };
-                write!(f, "{task}")?;
+                write_columns(f, &task)?;
             }
         }
-        if self.curr_stats.process.components.stack.is_some() {
+        if let Some(curr_stats) = &self.curr_stats.process.components.stack {
             let header = StackStatsHeaderDisplay {
                 tid: tid_display_option,
             };
-            write!(f, "{header}")?;
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
             let process = StackStatsValueDisplay {
                 tid: tid_display_option,
+                pid_color: self.options.color_by_pid,
                 id: &self.curr_stats.process.id,
-                curr_stats: self.curr_stats.process.components.stack.as_ref().unwrap(),
+                curr_stats,
             };
-            write!(f, "{process}")?;
+            write_columns(f, &process)?;
             for stats in self.curr_stats.task.values() {
+                let Some(curr_stats) = &stats.components.stack else {
+                    continue;
+                };
                 let task = StackStatsValueDisplay {
                     tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
                     id: &stats.id,
-                    curr_stats: stats.components.stack.as_ref().unwrap(),
+                    curr_stats,
                 };
-                write!(f, "{task}")?;
+                write_columns(f, &task)?;
             }
+        } else if self.options.blank_components
+            && self.curr_stats.process.components.restricted.stack
+        {
+            let header = StackStatsHeaderDisplay {
+                tid: tid_display_option,
+            };
+            self.write_restricted(f, tid_display_option, header, &[7, 7], |c| {
+                c.restricted.stack
+            })?;
         }
-        if self.curr_stats.process.components.io.is_some() {
+        if let Some(curr_stats) = &self.curr_stats.process.components.io {
             let header = IoStatsHeaderDisplay {
                 tid: tid_display_option,
+                change: self.options.change,
+                ops: self.options.io_ops,
             };
-            write!(f, "{header}")?;
-            let process = IoStatsValueDisplay {
-                tid: tid_display_option,
-                id: &self.curr_stats.process.id,
-                prev_stats: self.prev_stats.process.components.io.as_ref().unwrap(),
-                curr_stats: self.curr_stats.process.components.io.as_ref().unwrap(),
-            };
-            write!(f, "{process}")?;
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            if let Some(prev_stats) = &self.prev_stats.process.components.io {
+                let process = IoStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &self.curr_stats.process.id,
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.io,
+                    ops: self.options.io_ops,
+                };
+                write_columns(f, &process)?;
+            }
             for (tid, stats) in &self.curr_stats.task {
                 let Some(prev_stats) = self.prev_stats.task.get(tid) else {
                     continue;
                 };
+                let (Some(prev_stats), Some(curr_stats)) =
+                    (&prev_stats.components.io, &stats.components.io)
+                else {
+                    continue;
+                };
                 let task = IoStatsValueDisplay {
                     tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
                     id: &stats.id,
-                    prev_stats: prev_stats.components.io.as_ref().unwrap(),
-                    curr_stats: stats.components.io.as_ref().unwrap(),
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.io,
+                    ops: self.options.io_ops,
                 };
-                write!(f, "{task}")?;
+                write_columns(f, &task)?;
             }
+        } else if self.options.blank_components && self.curr_stats.process.components.restricted.io
+        {
+            let header = IoStatsHeaderDisplay {
+                tid: tid_display_option,
+                change: self.options.change,
+                ops: self.options.io_ops,
+            };
+            let widths: &[usize] = match self.options.io_ops {
+                true => &[9, 9, 9, 9, 9, 7],
+                false => &[9, 9, 9, 7],
+            };
+            self.write_restricted(f, tid_display_option, header, widths, |c| c.restricted.io)?;
         }
-        if self.curr_stats.process.components.ctx_switch.is_some() {
+        if let Some(curr_stats) = &self.curr_stats.process.components.ctx_switch {
             let header = CtxSwitchStatsHeaderDisplay {
                 tid: tid_display_option,
+                change: self.options.change,
             };
-            write!(f, "{header}")?;
-            let process = CtxSwitchStatsValueDisplay {
-                tid: tid_display_option,
-                id: &self.curr_stats.process.id,
-                prev_stats: self
-                    .prev_stats
-                    .process
-                    .components
-                    .ctx_switch
-                    .as_ref()
-                    .unwrap(),
-                curr_stats: self
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            if let Some(prev_stats) = &self.prev_stats.process.components.ctx_switch {
+                let process = CtxSwitchStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &self.curr_stats.process.id,
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.ctx_switch,
+                    outlier: false,
+                };
+                write_columns(f, &process)?;
+            }
+            let mut threshold = None;
+            if self.options.highlight_outliers {
+                let rates = self
                     .curr_stats
-                    .process
-                    .components
-                    .ctx_switch
-                    .as_ref()
-                    .unwrap(),
-            };
-            write!(f, "{process}")?;
+                    .task
+                    .iter()
+                    .filter_map(|(tid, stats)| {
+                        let prev = self
+                            .prev_stats
+                            .task
+                            .get(tid)?
+                            .components
+                            .ctx_switch
+                            .as_ref()?;
+                        Some(nivcsw_rate(prev, stats.components.ctx_switch.as_ref()?))
+                    })
+                    .collect::<Vec<f64>>();
+                threshold = outlier_threshold(&rates);
+            }
             for (tid, stats) in &self.curr_stats.task {
                 let Some(prev_stats) = self.prev_stats.task.get(tid) else {
                     continue;
                 };
+                let (Some(prev_stats), Some(curr_stats)) = (
+                    &prev_stats.components.ctx_switch,
+                    &stats.components.ctx_switch,
+                ) else {
+                    continue;
+                };
                 let task = CtxSwitchStatsValueDisplay {
                     tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &stats.id,
+                    prev_stats,
+                    curr_stats,
+                    change: self.options.change,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                    decimals: self.options.precision.ctx_switch,
+                    outlier: threshold
+                        .is_some_and(|threshold| threshold < nivcsw_rate(prev_stats, curr_stats)),
+                };
+                write_columns(f, &task)?;
+            }
+        }
+
+        if let Some(curr_stats) = &self.curr_stats.process.components.signal {
+            let header = SignalStatsHeaderDisplay {
+                tid: tid_display_option,
+            };
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            let process = SignalStatsValueDisplay {
+                tid: tid_display_option,
+                pid_color: self.options.color_by_pid,
+                id: &self.curr_stats.process.id,
+                curr_stats,
+            };
+            write_columns(f, &process)?;
+            for stats in self.curr_stats.task.values() {
+                let Some(curr_stats) = &stats.components.signal else {
+                    continue;
+                };
+                let task = SignalStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
                     id: &stats.id,
-                    prev_stats: prev_stats.components.ctx_switch.as_ref().unwrap(),
-                    curr_stats: stats.components.ctx_switch.as_ref().unwrap(),
+                    curr_stats,
                 };
-                write!(f, "{task}")?;
+                write_columns(f, &task)?;
             }
         }
 
+        if let Some(curr_stats) = &self.curr_stats.process.components.oom {
+            let header = OomStatsHeaderDisplay {
+                tid: tid_display_option,
+            };
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            let process = OomStatsValueDisplay {
+                tid: tid_display_option,
+                pid_color: self.options.color_by_pid,
+                id: &self.curr_stats.process.id,
+                curr_stats,
+                number_format: self.options.number_format,
+                number_align: self.options.number_align,
+            };
+            write_columns(f, &process)?;
+            for stats in self.curr_stats.task.values() {
+                let Some(curr_stats) = &stats.components.oom else {
+                    continue;
+                };
+                let task = OomStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &stats.id,
+                    curr_stats,
+                    number_format: self.options.number_format,
+                    number_align: self.options.number_align,
+                };
+                write_columns(f, &task)?;
+            }
+        }
+
+        if let Some(start) = self.curr_stats.process.id.start {
+            let header = StartHeaderDisplay {
+                tid: tid_display_option,
+            };
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            let process = StartValueDisplay {
+                tid: tid_display_option,
+                pid_color: self.options.color_by_pid,
+                id: &self.curr_stats.process.id,
+                start,
+            };
+            write_columns(f, &process)?;
+            for stats in self.curr_stats.task.values() {
+                let Some(start) = stats.id.start else {
+                    continue;
+                };
+                let task = StartValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &stats.id,
+                    start,
+                };
+                write_columns(f, &task)?;
+            }
+        }
+
+        if let Some(curr_stats) = &self.curr_stats.process.components.addresses {
+            let header = AddressStatsHeaderDisplay {
+                tid: tid_display_option,
+            };
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            let process = AddressStatsValueDisplay {
+                tid: tid_display_option,
+                pid_color: self.options.color_by_pid,
+                id: &self.curr_stats.process.id,
+                curr_stats,
+            };
+            write_columns(f, &process)?;
+            for stats in self.curr_stats.task.values() {
+                let Some(curr_stats) = &stats.components.addresses else {
+                    continue;
+                };
+                let task = AddressStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &stats.id,
+                    curr_stats,
+                };
+                write_columns(f, &task)?;
+            }
+        }
+
+        if let Some(curr_stats) = &self.curr_stats.process.components.fd {
+            let header = FdStatsHeaderDisplay {
+                tid: tid_display_option,
+            };
+            if self.options.header {
+                write_columns(f, &header)?;
+            }
+            let process = FdStatsValueDisplay {
+                tid: tid_display_option,
+                pid_color: self.options.color_by_pid,
+                id: &self.curr_stats.process.id,
+                curr_stats,
+            };
+            write_columns(f, &process)?;
+            for stats in self.curr_stats.task.values() {
+                let Some(curr_stats) = &stats.components.fd else {
+                    continue;
+                };
+                let task = FdStatsValueDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.options.color_by_pid,
+                    id: &stats.id,
+                    curr_stats,
+                };
+                write_columns(f, &task)?;
+            }
+        } else if self.options.blank_components && self.curr_stats.process.components.restricted.fd
+        {
+            let header = FdStatsHeaderDisplay {
+                tid: tid_display_option,
+            };
+            self.write_restricted(f, tid_display_option, header, &[9, 9], |c| c.restricted.fd)?;
+        }
+
         Ok(())
     }
 }
+impl TaskGroupStatsDisplay<'_> {
+    /// Write dashes for every task for which `restricted` tells that a component was skipped
+    fn write_restricted(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        tid: TidDisplayOption,
+        header: impl fmt::Display,
+        widths: &[usize],
+        restricted: impl Fn(&ComponentStats) -> bool,
+    ) -> fmt::Result {
+        if self.options.header {
+            write_columns(f, &header)?;
+        }
+        let tasks = [&self.curr_stats.process]
+            .into_iter()
+            .chain(self.curr_stats.task.values());
+        for stats in tasks {
+            if !restricted(&stats.components) {
+                continue;
+            }
+            let display = RestrictedValueDisplay {
+                tid,
+                pid_color: self.options.color_by_pid,
+                id: &stats.id,
+                widths,
+            };
+            write_columns(f, &display)?;
+        }
+        Ok(())
+    }
+}
+
+/// Marks the process and threads whose command changed between two consecutive samples
+///
+/// This is kept apart from [`TaskGroupStatsDisplay`], whose baseline may be older than the previous
+/// sample, so that each rename is marked once.
+pub struct TaskGroupCommandChangeDisplay<'a> {
+    pub prev_stats: &'a TaskGroupStats,
+    pub curr_stats: &'a TaskGroupStats,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+}
+impl<'a> fmt::Display for TaskGroupCommandChangeDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tid_display_option = if self.curr_stats.task.is_empty() {
+            TidDisplayOption::Pid
+        } else {
+            TidDisplayOption::Tid
+        };
+        if self.prev_stats.process.id.command != self.curr_stats.process.id.command {
+            let display = CommandChangeDisplay {
+                tid: tid_display_option,
+                pid_color: self.pid_color,
+                prev: &self.prev_stats.process.id,
+                curr: &self.curr_stats.process.id,
+            };
+            write_columns(f, &display)?;
+        }
+        for (tid, stats) in &self.curr_stats.task {
+            let Some(prev_stats) = self.prev_stats.task.get(tid) else {
+                continue;
+            };
+            if prev_stats.id.command != stats.id.command {
+                let display = CommandChangeDisplay {
+                    tid: tid_display_option,
+                    pid_color: self.pid_color,
+                    prev: &prev_stats.id,
+                    curr: &stats.id,
+                };
+                write_columns(f, &display)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replace the counters of the process in both samples by the sums over the threads present in both
+fn merge_threads(
+    prev_stats: &TaskGroupStats,
+    curr_stats: &TaskGroupStats,
+) -> (TaskGroupStats, TaskGroupStats) {
+    let mut prev_merged = prev_stats.clone();
+    let mut curr_merged = curr_stats.clone();
+    prev_merged.process.components = prev_stats.process.components.since_start();
+    curr_merged.process.components = curr_stats.process.components.since_start();
+    for (tid, stats) in &curr_stats.task {
+        let Some(prev) = prev_stats.task.get(tid) else {
+            continue;
+        };
+        prev_merged
+            .process
+            .components
+            .add_counters(&prev.components);
+        curr_merged
+            .process
+            .components
+            .add_counters(&stats.components);
+    }
+    (prev_merged, curr_merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use common::value::{strip_colors, COLUMN_START};
+
+    use crate::{
+        clock::{Clock, ManualClock},
+        cpu::CpuStats,
+        ctx_switch::CtxSwitchStats,
+        io::IoStats,
+        process::{ProcessId, RestrictedComponents},
+        read::{ProcId, Stats},
+    };
+
+    use super::*;
+
+    fn display_options() -> DisplayOptions {
+        DisplayOptions {
+            change: ChangeMode::PerSecond,
+            number_format: NumberFormat::Fixed,
+            number_align: NumberAlign::Right,
+            header: true,
+            precision: Precision::default(),
+            merge_threads: false,
+            thread_summary: false,
+            mem_delta: false,
+            children_faults: false,
+            rss_breakdown: false,
+            io_ops: false,
+            highlight_outliers: false,
+            color_by_pid: false,
+            blank_components: false,
+        }
+    }
+
+    /// A sample of a process with the counters of `cpu`, `io` and `ctx_switch` taken at `clock`
+    fn sample(clock: &impl Clock, user_time: u64, read_bytes: u64, nvcsw: u64) -> TaskGroupStats {
+        let time = clock.now();
+        let components = ComponentStats {
+            cpu: Some(CpuStats {
+                user_time,
+                system_time: 0,
+                guest_time: 0,
+                wait_time: 0,
+                blkio_delay: 0,
+                system_busy_time: None,
+                sched_time: None,
+                time,
+                processor: Some(0),
+                clock_ticks_per_second: 100,
+            }),
+            mem: None,
+            stack: None,
+            io: Some(IoStats {
+                read_bytes,
+                write_bytes: 0,
+                cancelled_write_bytes: 0,
+                blkio_swapin_delays: 0,
+                syscr: 0,
+                syscw: 0,
+                time,
+            }),
+            ctx_switch: Some(CtxSwitchStats {
+                nvcsw,
+                nivcsw: 0,
+                time,
+            }),
+            signal: None,
+            oom: None,
+            addresses: None,
+            fd: None,
+            restricted: RestrictedComponents::default(),
+        };
+        TaskGroupStats {
+            pid: 1,
+            process: Stats {
+                id: ProcessId {
+                    uid: 0,
+                    proc_id: ProcId { pid: 1, tid: None },
+                    command: "init".to_string(),
+                    state: 'S',
+                    start: None,
+                },
+                components,
+            },
+            task: BTreeMap::new(),
+        }
+    }
+
+    /// The value under `column` in the row of the table whose header has that column
+    fn column(report: &str, column: &str) -> String {
+        let lines = report.lines().collect::<Vec<_>>();
+        let header = lines
+            .iter()
+            .position(|line| line.split_whitespace().any(|c| c == column))
+            .unwrap();
+        let index = lines[header]
+            .split_whitespace()
+            .position(|c| c == column)
+            .unwrap();
+        let row = lines[header + 1].split_whitespace().collect::<Vec<_>>();
+        row[index].to_string()
+    }
+
+    /// The fields of each line of the alternate form of `display`
+    fn fields(display: &impl fmt::Display) -> Vec<Vec<String>> {
+        format!("{display:#}")
+            .lines()
+            .map(|line| {
+                let mut fields = line
+                    .split(COLUMN_START)
+                    .map(|field| strip_colors(field).trim().to_string())
+                    .collect::<Vec<_>>();
+                if fields.first().is_some_and(|field| field.is_empty()) {
+                    fields.remove(0);
+                }
+                fields
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rows_have_a_field_under_each_header() {
+        let clock = ManualClock::new(Instant::now());
+        let mut prev = sample(&clock, 100, 0, 10);
+        clock.advance(Duration::from_secs(1));
+        let mut curr = sample(&clock, 150, 300 * 1024, 130);
+        for stats in [&mut prev, &mut curr] {
+            stats.process.id.command = "my app".to_string();
+        }
+        let display = TaskGroupStatsDisplay {
+            prev_stats: &prev,
+            curr_stats: &curr,
+            options: display_options(),
+        };
+        let lines = fields(&display);
+        // A header and a row for each of CPU, IO and context switches
+        assert_eq!(lines.len(), 6);
+        for table in lines.chunks(2) {
+            let [header, row] = table else { unreachable!() };
+            assert_eq!(header.len(), row.len(), "{header:?} {row:?}");
+            assert_eq!(header.last().unwrap(), "Command");
+            assert_eq!(row.last().unwrap(), "my app");
+        }
+        assert_eq!(lines[0][..3], ["UID", "PID", "%usr"]);
+        assert_eq!(lines[1][..3], ["0", "1", "50.00"]);
+    }
+
+    #[test]
+    fn thread_summary_is_a_single_field() {
+        let clock = ManualClock::new(Instant::now());
+        let mut prev = sample(&clock, 0, 0, 0);
+        clock.advance(Duration::from_secs(1));
+        let mut curr = sample(&clock, 0, 0, 0);
+        for stats in [&mut prev, &mut curr] {
+            let mut task = stats.process.clone();
+            task.id.proc_id.tid = Some(2);
+            task.id.state = 'R';
+            stats.task.insert(2, task);
+        }
+        let display = TaskGroupStatsDisplay {
+            prev_stats: &prev,
+            curr_stats: &curr,
+            options: DisplayOptions {
+                thread_summary: true,
+                ..display_options()
+            },
+        };
+        let lines = fields(&display);
+        assert_eq!(lines.last().unwrap(), &["0", "1", "threads: R:1"]);
+    }
+
+    #[test]
+    fn maps_keep_only_the_monitored_processes() {
+        let mut prev_stats = BTreeMap::new();
+        let mut first_stats = BTreeMap::new();
+        let mut monitored = BTreeSet::new();
+        // Every resolution a process exits and a new PID appears
+        for resolution in 0..1000 {
+            monitored = (resolution..resolution + 10).collect::<BTreeSet<usize>>();
+            forget_unmonitored(&monitored, &mut [&mut prev_stats, &mut first_stats]);
+            for &p in &monitored {
+                prev_stats.insert(p, resolution);
+                first_stats.entry(p).or_insert(resolution);
+            }
+        }
+        assert!(prev_stats.keys().eq(&monitored));
+        assert!(first_stats.keys().eq(&monitored));
+        // The processes still monitored keep their first sample
+        assert_eq!(first_stats[&1000], 991);
+        assert_eq!(prev_stats[&1000], 999);
+
+        forget_unmonitored(&BTreeSet::new(), &mut [&mut prev_stats, &mut first_stats]);
+        assert!(prev_stats.is_empty());
+        assert!(first_stats.is_empty());
+    }
+
+    #[test]
+    fn rates_over_one_second() {
+        let clock = ManualClock::new(Instant::now());
+        let prev = sample(&clock, 100, 0, 10);
+        clock.advance(Duration::from_secs(1));
+        // Half of the ticks, 300 kB and 120 switches in that second
+        let curr = sample(&clock, 150, 300 * 1024, 130);
+        let report = strip_colors(&render_delta(&prev, &curr, display_options()));
+        assert_eq!(column(&report, "%usr"), "50.00", "{report}");
+        assert_eq!(column(&report, "kB_rd/s"), "300.00", "{report}");
+        assert_eq!(column(&report, "cswch/s"), "120.00", "{report}");
+    }
+
+    #[test]
+    fn rates_over_half_a_second() {
+        let clock = ManualClock::new(Instant::now());
+        let prev = sample(&clock, 0, 0, 0);
+        clock.advance(Duration::from_millis(500));
+        let curr = sample(&clock, 25, 100 * 1024, 60);
+        let report = strip_colors(&render_delta(&prev, &curr, display_options()));
+        assert_eq!(column(&report, "%usr"), "50.00", "{report}");
+        assert_eq!(column(&report, "kB_rd/s"), "200.00", "{report}");
+        assert_eq!(column(&report, "cswch/s"), "120.00", "{report}");
+    }
+
+    #[test]
+    fn totals_over_one_second() {
+        let clock = ManualClock::new(Instant::now());
+        let prev = sample(&clock, 100, 0, 10);
+        clock.advance(Duration::from_secs(1));
+        let curr = sample(&clock, 150, 300 * 1024, 130);
+        let options = DisplayOptions {
+            change: ChangeMode::Total,
+            ..display_options()
+        };
+        let report = strip_colors(&render_delta(&prev, &curr, options));
+        assert_eq!(column(&report, "usr(s)"), "0.50", "{report}");
+        assert_eq!(column(&report, "kB_rd"), "300", "{report}");
+        assert_eq!(column(&report, "cswch"), "120", "{report}");
+    }
+}
@@ -1,20 +1,111 @@
 use std::{
-    collections::{btree_map, BTreeMap},
-    time::Duration,
+    collections::{btree_map, BTreeMap, BTreeSet},
+    fmt::Write as _,
+    io::{IsTerminal, Write},
+    num::{NonZeroUsize, ParseIntError},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
 };
 
-use clap::Parser;
+use clap::{CommandFactory, Parser, ValueEnum};
+use common::{
+    value::{strip_colors, NumberAlign, NumberFormat, COLUMN_START},
+    ChangeMode,
+};
+#[cfg(feature = "msgpack")]
+use pidstat::format::write_msgpack;
 use pidstat::{
-    read::{read_task_group_stats, ComponentOptions, ReadPidOptions, TaskGroupStats},
-    TaskGroupStatsDisplay,
+    adaptive::AdaptiveInterval,
+    alert::{AlertDebouncer, AlertMetric, Thresholds},
+    baseline::{Baseline, BaselineComparisonHeaderDisplay, BaselineComparisonValueDisplay},
+    columns::{ColumnsDisplay, COLUMNS},
+    forget_unmonitored,
+    format::{read_capture, write_graphite},
+    leak::LeakWatchdog,
+    metadata::CaptureMetadata,
+    output::{GraphiteSink, PidFiles},
+    process::{
+        CommandDisplay, IdHeaderDisplay, IdValueDisplay, SampleIntervalDisplay, StateChangeDisplay,
+        TidDisplayOption,
+    },
+    read::{
+        read_task_group_stats, read_unit_pid, read_user_uid, Capabilities, ComponentOptions,
+        NameMatcher, ProcId, ReadPidOptions, ReadStatsError, ReadStatsOptions, ReadTidOptions,
+        StatLayout, Stats, TaskGroupStats, TaskOptions,
+    },
+    record::{task_group_records, EventRecord, SampleRecord},
+    render_delta,
+    select::{AllOf, AnyOf, Except, Locked, PidSelector, Pids, Unit},
+    smooth::CounterSmoother,
+    snapshot::Snapshot,
+    summary::{IoSummary, WaitSummary},
+    DisplayOptions, Precision, TaskGroupCommandChangeDisplay, TaskGroupStatsDisplay,
 };
+use regex::Regex;
+use tracing::{debug, info, warn, Level};
 
 #[derive(Debug, Clone, Parser)]
 struct Cli {
+    /// Print the version and exit; with `verbose`, also what the kernel offers that some columns
+    /// depend on, like delay accounting
+    #[clap(short('V'), long)]
+    version: bool,
+    /// The PID to monitor, or `ALL` to monitor every process
     #[clap(short, long)]
-    pid: Option<usize>,
-    #[clap(short('G'), long)]
+    pid: Option<PidSelection>,
+    /// Only monitor processes whose command matches this, as selected by `name-match`
+    #[clap(short('G'), long, group = "names")]
     process_name: Option<String>,
+    /// Only monitor processes whose command matches any of these, as selected by `name-match`,
+    /// like `nginx,redis,postgres`; may be repeated.
+    ///
+    /// A process that matches several names is monitored once.
+    #[clap(long, value_delimiter = ',', group = "names")]
+    process_name_list: Vec<String>,
+    /// How `process-name` is matched against the command:
+    ///
+    /// exact      The command is exactly `process-name`.
+    ///
+    /// substring  The command contains `process-name`.
+    ///
+    /// regex      The command matches the regular expression `process-name` somewhere; anchor it
+    ///            with `^` and `$` to match the whole command.
+    ///
+    /// The command is the kernel's `comm`, which is truncated to 15 bytes, so an exact match
+    /// against a longer program name never succeeds.
+    #[clap(long, value_enum, default_value = "substring", requires = "names")]
+    name_match: NameMatch,
+    /// Resolve the selection again at the start of every interval, before reading the stats.
+    ///
+    /// This is the default. A process that starts matching is read for the first time at the
+    /// start of the next interval and, like every newly monitored process, reported from the
+    /// interval after; a process that stops matching, say by being renamed, is dropped at once.
+    #[clap(long, conflicts_with = "no_refresh_match")]
+    refresh_match: bool,
+    /// Monitor the processes selected at the start for the whole run, even if they are later
+    /// renamed or other processes start matching
+    #[clap(long)]
+    no_refresh_match: bool,
+    /// Only monitor processes that started at least this long ago, like `30s`, `5m` or `1h`.
+    ///
+    /// The age of a process is the time since boot (`btime` in `/proc/stat`) plus its `starttime`
+    /// in clock ticks, subtracted from the current time, so it is precise to about a second. If
+    /// the boot time cannot be read, no process is selected and a warning is printed.
+    #[clap(long, value_parser = parse_duration)]
+    min_lifetime: Option<Duration>,
+    /// Only monitor the processes of this systemd unit, such as `nginx.service`.
+    ///
+    /// The processes are looked up in the unit's cgroup under `system.slice` on every interval.
+    #[clap(long)]
+    unit: Option<String>,
+    /// Only monitor processes whose real UID is this
+    #[clap(long, conflicts_with = "user")]
+    uid: Option<usize>,
+    /// Only monitor processes whose real user is this, looked up through the name service switch
+    /// like `id` does, so that users of LDAP or sssd are found too
+    #[clap(long)]
+    user: Option<String>,
     /// Report I/O statistics (kernels 2.6.20 and later only).
     /// The following values may be displayed:
     ///
@@ -52,6 +143,10 @@ struct Cli {
     ///         The command name of the task.
     #[clap(short('d'), long)]
     io: bool,
+    /// Report the read and write system calls with `io`, as `rops/s` and `wops/s`, to tell the
+    /// average size of an operation
+    #[clap(long, requires = "io")]
+    io_ops: bool,
     /// Report CPU utilization.
     ///
     /// When reporting statistics for individual tasks, the
@@ -81,6 +176,13 @@ struct Cli {
     /// %wait  Percentage of CPU spent by the task while waiting
     ///        to run.
     ///
+    /// %blkio Percentage of time the task spent blocked waiting
+    ///        for block I/O to complete, including swap-in. Only
+    ///        non-zero if delay accounting is enabled (the
+    ///        kernel.task_delayacct sysctl or the delayacct boot
+    ///        option). Unlike %wait, this is not time spent
+    ///        waiting for a CPU.
+    ///
     /// %CPU   Total percentage of CPU time used by the task. In
     ///        an SMP environment, the task's CPU usage will be
     ///        divided by the total number of CPU's if option -I
@@ -92,6 +194,22 @@ struct Cli {
     ///        The command name of the task.
     #[clap(short('u'), long)]
     cpu: bool,
+    /// What `cpu` reports CPU usage relative to:
+    ///
+    /// core            A single CPU.
+    ///
+    /// system-relative Also the share of the CPU time all CPUs of the system spent doing
+    ///                 anything but idling, in the %share column.
+    #[clap(long, value_enum, default_value = "core")]
+    cpu_mode: CpuMode,
+    /// Also report the CPU time `cpu` accounts to the task in `/proc/<pid>/stat` (utime and stime,
+    /// including guest time) in the %acct column, next to the time the scheduler ran it by
+    /// `/proc/<pid>/schedstat` in the %sched column, to spot accounting anomalies.
+    ///
+    /// The scheduler's figure is precise to the nanosecond while the accounted one is sampled at
+    /// clock ticks, so they differ by a few percent on short intervals.
+    #[clap(long, requires = "cpu")]
+    cpu_reconcile: bool,
     /// Report page faults and memory utilization.
     ///
     /// When reporting statistics for individual tasks, the
@@ -129,6 +247,17 @@ struct Cli {
     ///        The command name of the task.
     #[clap(short('r'), long)]
     mem: bool,
+    /// Report the change of VSZ and RSS since the previous sample with `mem`, as `dVSZ` and `dRSS`
+    #[clap(long, requires = "mem")]
+    mem_delta: bool,
+    /// Report the fault rates of the children that were waited for with `mem`, as `cminflt/s`
+    /// and `cmajflt/s`, to tell the faults of short-lived children of a process pool
+    #[clap(long, requires = "mem")]
+    children_faults: bool,
+    /// Split RSS into anonymous memory, file mappings, and shared memory with `mem`, as
+    /// `RssAnon`, `RssFile`, and `RssShm`; shown as dashes on kernels older than 4.5
+    #[clap(long, requires = "mem")]
+    rss_breakdown: bool,
     /// Report stack utilization.  The following values may be
     /// displayed:
     ///
@@ -152,6 +281,17 @@ struct Cli {
     ///        The command name of the task.
     #[clap(short('s'), long)]
     stack: bool,
+    /// Where `stack` reads StkSize from:
+    ///
+    /// smaps  The size of the main `[stack]` mapping in `/proc/<pid>/smaps`.
+    ///
+    /// maps   The total size of the stack mappings in `/proc/<pid>/maps`, including the
+    ///        `[stack:<tid>]` mappings of each thread on Linux 3.4 to 4.4; a thread is sized
+    ///        by its own mappings where they are labeled.
+    ///
+    /// StkRef is always the referenced memory of the main `[stack]` mapping in `smaps`.
+    #[clap(long, value_enum, default_value = "smaps")]
+    stack_source: StackSource,
     /// Report task switching activity (kernels 2.6.23 and later
     /// only).  The following values may be displayed:
     ///
@@ -181,59 +321,1628 @@ struct Cli {
     ///        The command name of the task.
     #[clap(short('w'), long)]
     ctx_switch: bool,
+    /// Highlight the threads of `ctx-switch` and `task` whose nvcswch/s is more than two standard
+    /// deviations above the mean of their thread group, a sign of threads starved for CPU.
+    ///
+    /// It takes at least six threads for one to stand out this far.
+    #[clap(long, requires_all = ["ctx_switch", "task"])]
+    highlight_outliers: bool,
+    /// Report the signal masks of the task.  The following values may
+    /// be displayed:
+    ///
+    /// Blocked
+    ///        The signals blocked by the task.
+    ///
+    /// Ignored
+    ///        The signals ignored by the task.
+    ///
+    /// Caught The signals the task has installed handlers for.
+    ///
+    /// Only the first 31 signals are reported; real-time signals are
+    /// not.
+    #[clap(long)]
+    signals: bool,
+    /// Report how likely the task is to be picked by the OOM killer.  The
+    /// following values may be displayed:
+    ///
+    /// oom_score
+    ///        The badness the OOM killer picks its victim by; the task
+    ///        with the highest score is killed first.
+    ///
+    /// oom_adj
+    ///        The adjustment added to the badness, from -1000 (never
+    ///        kill) to 1000.
+    #[clap(long)]
+    oom: bool,
+    /// Report when the task started, in UTC, as `START`: the time if it started today, else the
+    /// month and day if it started this year, else the year, like the STIME of `ps`
+    #[clap(long)]
+    start_time: bool,
+    /// Report addresses of the task in hexadecimal, for low-level
+    /// debugging.  The following values may be displayed:
+    ///
+    /// StartStack
+    ///        The bottom of the stack.
+    ///
+    /// KStkESP
+    ///        The current stack pointer.
+    ///
+    /// KStkEIP
+    ///        The current instruction pointer.
+    ///
+    /// WChan  The kernel function the task is waiting in.
+    ///
+    /// Addresses the kernel does not disclose to pidstat are shown as -.
+    #[clap(long)]
+    addresses: bool,
+    /// Report the open file descriptors of the task.  The following
+    /// values may be displayed:
+    ///
+    /// FDs    The number of open file descriptors.
+    ///
+    /// Sockets
+    ///        The number of open file descriptors that are sockets.
+    #[clap(long)]
+    sockets: bool,
+    /// Read the delays of `%wait`, `%blkio` and `iodelay` and the context switches of `cswch/s`
+    /// and `nvcswch/s` from the taskstats interface of netlink, which needs `CAP_NET_ADMIN`,
+    /// instead of from `/proc`.
+    ///
+    /// Falls back to `/proc` where taskstats is unavailable or pidstat was built without the
+    /// `taskstats` feature.
+    #[clap(long)]
+    taskstats: bool,
+    /// Try to read `stack`, `io` and `sockets` of the tasks of other users even when not running
+    /// as root.
+    ///
+    /// By default, unprivileged runs skip them, since the kernel would deny the reads, and show
+    /// dashes in their place unless `no-blank-components` is given.
+    #[clap(long)]
+    read_unowned: bool,
+    /// Exit when `/proc/<pid>/stat` does not have exactly the fields that the running kernel is
+    /// known to write by its release, or on any other malformed data, instead of reading whatever
+    /// is there and taking the process for gone
+    #[clap(long)]
+    strict: bool,
+    /// Leave out the tables of the components that could not be read for a process, like those
+    /// of other users when unprivileged, instead of showing dashes
+    #[clap(long)]
+    no_blank_components: bool,
+    /// Only read the `/proc` files that any user may read, like `stat` and `status`, so that no
+    /// read is ever denied and flagged by auditing.
+    ///
+    /// `stack`, `io`, `sockets`, `cpu-reconcile` and `taskstats` are left out with a notice, since
+    /// they need privileges for the tasks of other users, and `%wait` of `cpu` reads as zero
+    /// since it comes from `/proc/<pid>/schedstat`.
+    #[clap(long)]
+    safe_mode: bool,
     #[clap(short('t'), long)]
     task: bool,
-    /// Specify the amount of time in seconds between each report
-    #[clap(default_value = "1")]
-    interval: u64,
+    /// Report the sum of the threads of each process with `task` as its row instead of the
+    /// process's own counters, to check them against each other.
+    ///
+    /// Only the threads present in both samples are summed.
+    #[clap(long, requires = "task")]
+    merge_threads: bool,
+    /// Report how many threads of each process with `task` are in each state, like `R:2 S:40 D:1`,
+    /// instead of a row for every thread
+    #[clap(long, requires = "task")]
+    thread_summary: bool,
+    /// Report at most this many threads of each process with `task`, the lowest TIDs first
+    #[clap(long, requires = "task")]
+    max_tasks: Option<usize>,
+    /// Report the threads of the thread group whose leader has this PID.
+    ///
+    /// Same as `--pid <TGID> --task`.
+    #[clap(long, conflicts_with = "pid")]
+    tgid: Option<usize>,
+    /// Drop pidstat's own PID from the monitored set.
+    ///
+    /// This is the default when selecting processes by `process-name` or with `--pid ALL`.
+    #[clap(long, conflicts_with = "include_self")]
+    exclude_self: bool,
+    /// Keep pidstat's own PID in the monitored set even when selecting processes by `process-name`
+    /// or with `--pid ALL`
+    #[clap(long)]
+    include_self: bool,
+    /// How floating point values are rendered.
+    ///
+    /// Neither format is locale-aware.
+    #[clap(long, value_enum, default_value = "fixed")]
+    number_format: NumberFormat,
+    /// The number of decimals of rates and percentages
+    #[clap(long, default_value = "2")]
+    precision: usize,
+    /// The number of decimals of `cpu`, overriding `precision`
+    #[clap(long)]
+    cpu_precision: Option<usize>,
+    /// The number of decimals of `mem`, overriding `precision`
+    #[clap(long)]
+    mem_precision: Option<usize>,
+    /// The number of decimals of `io`, overriding `precision`
+    #[clap(long)]
+    io_precision: Option<usize>,
+    /// The number of decimals of `ctx-switch`, overriding `precision`
+    #[clap(long)]
+    ctx_switch_precision: Option<usize>,
+    /// How floating point values line up in their columns
+    #[clap(long, value_enum, default_value = "right")]
+    align: NumberAlign,
+    /// Color the IDs and command of each process, and of its threads, by its PID so that its rows
+    /// are easy to follow across intervals.
+    ///
+    /// Like the other colors, it is left out with `field-sep`.
+    #[clap(long)]
+    color_by_pid: bool,
+    /// Warn on stderr when a process's RSS grows on every interval of the leak window by at least
+    /// this many megabytes in total
+    #[clap(long, requires = "mem")]
+    leak_threshold: Option<u64>,
+    /// The number of intervals over which RSS growth is watched for `leak-threshold`
+    #[clap(long, default_value = "10")]
+    leak_window: NonZeroUsize,
+    /// Print a summary once the run ends after `count` or `duration`: with `io`, the total bytes
+    /// read from and written to disk by all monitored processes and the average throughput; with
+    /// `cpu`, the 50th, 95th and 99th percentiles of the `%wait` of each interval of each process
+    #[clap(long)]
+    summary: bool,
+    /// Take one sample, wait for `interval` seconds, take a final sample, print the total change of
+    /// each metric between the two samples and exit
+    #[clap(long)]
+    diff_mode: bool,
+    /// Print the rates between two snapshots written by `snapshot-on-signal` and exit, without
+    /// sampling anything.
+    ///
+    /// Processes in only one of the snapshots are skipped with a warning.
+    #[clap(long, num_args = 2, value_names = ["PREV", "CURR"], conflicts_with = "diff_mode")]
+    diff: Option<Vec<PathBuf>>,
+    /// Take one sample, print the rates averaged over the lifetime of each task and exit, without
+    /// waiting for a second sample; the same as an `interval` of 0
+    #[clap(long, conflicts_with = "diff_mode")]
+    since_boot: bool,
+    /// Report the total change of each metric since pidstat started watching each process instead
+    /// of the per-second rates of the last interval
+    #[clap(long, conflicts_with = "diff_mode")]
+    cumulative: bool,
+    /// Only write errors to stderr, without warnings like `leak-threshold` and informational
+    /// messages
+    #[clap(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Write informational messages to stderr, and debugging messages when repeated
+    #[clap(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Log every `/proc` file read, its outcome, and the parsed values to stderr
+    #[clap(long)]
+    trace_reads: bool,
+    /// Print the column headers only once instead of on every interval.
+    ///
+    /// This is the default when exactly one process is monitored and stdout is a terminal.
+    /// Only applies when a single component is reported.
+    #[clap(long, conflicts_with = "no_compact")]
+    compact: bool,
+    /// Print the column headers on every interval
+    #[clap(long)]
+    no_compact: bool,
+    /// Alert when a process's `%CPU` exceeds this percentage
+    #[clap(long)]
+    cpu_threshold: Option<f64>,
+    /// Alert when a process's `%MEM` exceeds this percentage
+    #[clap(long)]
+    mem_threshold: Option<f64>,
+    /// Alert when a process reads and writes more than this many kilobytes per second
+    #[clap(long)]
+    io_threshold: Option<f64>,
+    /// Run this command through `sh -c` when a process starts exceeding a threshold.
+    ///
+    /// The command does not run again for the same process and metric until the metric drops back
+    /// below its threshold.
+    /// The offending process is passed in the environment variables `PIDSTAT_PID`,
+    /// `PIDSTAT_COMMAND`, `PIDSTAT_METRIC` (`cpu`, `mem` or `io`) and `PIDSTAT_VALUE`.
+    /// Without it, alerts are written to stderr.
+    #[clap(long)]
+    on_threshold: Option<String>,
+    /// Ring the terminal bell on stderr when a process starts exceeding a threshold, debounced like
+    /// `on-threshold`
+    #[clap(long)]
+    bell: bool,
+    /// Send a desktop notification naming the process and the metric when a process starts
+    /// exceeding a threshold, debounced like `on-threshold`.
+    ///
+    /// Needs `notify-send` of libnotify.
+    #[cfg(feature = "notify")]
+    #[clap(long)]
+    notify: bool,
+    /// Sample and render as usual but discard the output, to profile the readers
+    #[clap(long, hide = true)]
+    no_output: bool,
+    /// Write the number of processes sampled on each interval and the time it took to stderr
+    #[clap(long, hide = true)]
+    self_stats: bool,
+    /// Print the real time between the last two samples of each process and how far it is off
+    /// `interval` before its rows
+    #[clap(long)]
+    jitter: bool,
+    /// Only report a process in the tables on the intervals its state, like `R`, `S`, `D` or `Z`,
+    /// differs from the previous interval's, after a `state: S -> D` line
+    #[clap(long)]
+    on_state_change: bool,
+    /// Halve the interval, down to `min-interval`, after an interval in which any process was
+    /// busy, and double it, up to `max-interval`, after one in which all were idle.
+    ///
+    /// A process is busy if its `%CPU` exceeds `adaptive-cpu` or it reads and writes more than
+    /// `adaptive-io` kilobytes per second. Rates stay correct as they are computed over the time
+    /// that actually elapsed.
+    #[clap(long)]
+    adaptive: bool,
+    /// The `%CPU` above which a process is busy for `adaptive`
+    #[clap(long, default_value = "50", requires = "adaptive")]
+    adaptive_cpu: f64,
+    /// The kilobytes read and written per second above which a process is busy for `adaptive`
+    #[clap(long, default_value = "1024", requires = "adaptive")]
+    adaptive_io: f64,
+    /// The shortest interval of `adaptive`, like `250ms`
+    #[clap(long, value_parser = parse_duration, default_value = "250ms", requires = "adaptive")]
+    min_interval: Duration,
+    /// The longest interval of `adaptive`, like `1m`
+    #[clap(long, value_parser = parse_duration, default_value = "1m", requires = "adaptive")]
+    max_interval: Duration,
+    /// Also write the samples of each process as NDJSON to `<pid>-<command>.ndjson` in this
+    /// directory.
+    ///
+    /// A failed read is written as a record like
+    /// `{"type":"error","pid":1234,"kind":"no_such_process"}`, to `<pid>.ndjson` if the process was
+    /// never sampled. Existing files are truncated.
+    #[clap(long)]
+    output_dir: Option<PathBuf>,
+    /// What to print for the first sample of a process, which has no previous sample to compute
+    /// rates against:
+    ///
+    /// skip      Nothing; its first row comes after one interval.
+    ///
+    /// zero      A row of zero rates right away.
+    ///
+    /// absolute  A row of the totals since the process started right away.
+    #[clap(long, value_enum, default_value = "skip")]
+    first_sample: FirstSample,
+    /// Stamp the records of `output-dir` and `--format graphite` with the seconds since the first
+    /// sample instead of the seconds since the Unix epoch
+    #[clap(long)]
+    relative_time: bool,
+    /// Keep the rates of each process since its first sample of the run in this file for
+    /// `compare-baseline`
+    #[clap(long)]
+    save_baseline: Option<PathBuf>,
+    /// Compare each interval against the samples saved by `save-baseline` in this file instead of
+    /// against the previous interval.
+    ///
+    /// Processes are matched by command.
+    #[clap(long)]
+    compare_baseline: Option<PathBuf>,
+    /// Re-read the components of only absolute values (`stack`, `oom` and `sockets`) every this many intervals
+    /// and reuse their previous values in between.
+    ///
+    /// Rates are still sampled every interval.
+    #[clap(long, default_value = "1")]
+    slow_poll: NonZeroUsize,
+    /// Sample every interval but only report every this many samples, to sample finely for
+    /// accurate rates while keeping the output small
+    #[clap(long, default_value = "1")]
+    print_every: NonZeroUsize,
+    /// Report the rates over the `print-every` intervals since the previous report instead of
+    /// over the last interval
+    #[clap(long, requires = "print_every")]
+    print_window: bool,
+    /// Report the rates since the start of the current bucket of this many intervals instead of
+    /// over the last interval, so that the last report of each bucket is its average.
+    ///
+    /// Buckets do not overlap: one ends at every this many samples of the run, counted from the
+    /// first sample, and the next starts from there. A process that starts being monitored later
+    /// starts its first bucket at its first sample, so that bucket is shorter.
+    #[clap(long, conflicts_with_all = ["cumulative", "print_window"])]
+    reset_every: Option<NonZeroUsize>,
+    /// Spread the bursts of counters that the kernel only updates every so often, like `%blkio`,
+    /// `iodelay` and the I/O of some filesystems, over the intervals between their updates instead
+    /// of reporting a spike followed by zeros.
+    ///
+    /// The rates are then estimates: each increase is assumed to have accumulated evenly since
+    /// the previous one and is reported over as many intervals as it took to arrive.
+    #[clap(long)]
+    smooth_counters: bool,
+    /// How each interval is reported:
+    ///
+    /// table     Tables on stdout.
+    ///
+    /// graphite  Lines of the Graphite plaintext protocol sent to `graphite-host` over TCP.
+    ///
+    /// msgpack   The records of `output-dir` as MessagePack on stdout, each prefixed by its length
+    ///           as a big-endian 32-bit integer; needs the `msgpack` feature.
+    #[clap(long, value_enum, default_value = "table")]
+    format: OutputFormat,
+    /// Begin the `table` output with `#` comments of the host, the kernel release, the interval,
+    /// the start time in UTC and the clock ticks per second, to record the context of a capture
+    #[clap(long)]
+    metadata_header: bool,
+    /// Write the samples of a capture of `output-dir` or `--format msgpack` at this path as NDJSON
+    /// to stdout and exit
+    #[clap(long)]
+    replay: Option<PathBuf>,
+    /// The `host:port` of the Graphite plaintext listener for `--format graphite`
+    #[clap(long, required_if_eq("format", "graphite"))]
+    graphite_host: Option<String>,
+    /// On `SIGUSR2`, write the full stats of every monitored process from the current interval as
+    /// JSON to a new timestamped file in this directory, for `diff`
+    #[clap(long)]
+    snapshot_on_signal: Option<PathBuf>,
+    /// Flush stdout after every record instead of once per interval, for consumers that read the
+    /// output as it streams
+    #[clap(long)]
+    line_buffered: bool,
+    /// Separate the columns of the tables by this string instead of padding them, e.g. `$'\t'`,
+    /// for `awk` and `cut`.
+    ///
+    /// Colors are left out. A command is a single field even if it contains whitespace.
+    #[clap(long)]
+    field_sep: Option<String>,
+    /// Print the processes that would be monitored and exit without sampling
+    #[clap(long)]
+    dry_run: bool,
+    /// Print the name, enabling option, unit and meaning of every column and exit
+    #[clap(long)]
+    help_columns: bool,
+    /// Specify the amount of time in seconds between each report, like `0.2`.
+    ///
+    /// Send `SIGUSR1` to print the Average since the previous Average report (or since the start)
+    /// and begin a new averaging phase.
+    ///
+    /// `0` reports the rates since each task started, like `since-boot`; any other value must be
+    /// greater than 0 and at least a nanosecond.
+    #[clap(default_value = "1", value_parser = parse_interval)]
+    interval: Duration,
+    /// Exit after this many reports instead of running until interrupted
+    count: Option<usize>,
+    /// Exit after running for this long, e.g. `30s` or `5m`, printing the Average over the whole
+    /// run first; with `count`, whichever comes first ends the run.
+    ///
+    /// It must be at least one `interval`.
+    #[clap(long, value_parser = parse_duration, conflicts_with = "since_boot")]
+    duration: Option<Duration>,
+    /// Keep a `sample <n>/<count>` line updated in place on stderr
+    #[clap(long, requires = "count")]
+    progress: bool,
 }
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
-    let components = ComponentOptions {
+    let mut cli = Cli::parse();
+    if cli.version {
+        print_version(cli.verbose != 0).await;
+        return;
+    }
+    if let Some(path) = &cli.replay {
+        if let Err(e) = replay(path) {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cli.help_columns {
+        print!("{}", ColumnsDisplay { columns: COLUMNS });
+        return;
+    }
+    let selected = [
+        cli.pid.is_some(),
+        cli.tgid.is_some(),
+        cli.process_name.is_some(),
+        !cli.process_name_list.is_empty(),
+        cli.unit.is_some(),
+        cli.uid.is_some(),
+        cli.user.is_some(),
+    ];
+    if cli.diff.is_none() && !selected.contains(&true) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "Provide at least one of `--pid`, `--tgid`, `--process-name`, `--process-name-list`, `--unit`, `--uid` or `--user`",
+            )
+            .exit();
+    }
+    let level = match (cli.trace_reads, cli.quiet, cli.verbose) {
+        (true, _, _) => Level::TRACE,
+        (false, true, _) => Level::ERROR,
+        (false, false, 0) => Level::WARN,
+        (false, false, 1) => Level::INFO,
+        (false, false, _) => Level::DEBUG,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .init();
+    // Every read on Linux would fail as if the processes were gone
+    if cfg!(target_os = "linux") && cli.diff.is_none() && !Capabilities::read().await.procfs {
+        eprintln!("/proc is not mounted; pidstat requires procfs");
+        std::process::exit(1);
+    }
+    if cli.task && !cfg!(target_os = "linux") {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "`--task` reads threads, which are only supported on Linux",
+            )
+            .exit();
+    }
+    if let Some(tgid) = cli.tgid {
+        if let Err(e) = (ReadTidOptions { tgid }).read_tid().await {
+            eprintln!("TGID {tgid}: {e}");
+            std::process::exit(1);
+        }
+        cli.pid = Some(PidSelection::Pid(tgid));
+        cli.task = true;
+    }
+    let process_names = cli
+        .process_name
+        .iter()
+        .chain(&cli.process_name_list)
+        .map(|name| match cli.name_match {
+            NameMatch::Exact => NameMatcher::Exact(name.clone()),
+            NameMatch::Substring => NameMatcher::Substring(name.clone()),
+            NameMatch::Regex => NameMatcher::Regex(Regex::new(name).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            })),
+        })
+        .collect::<Vec<_>>();
+    if let Some(user) = &cli.user {
+        match read_user_uid(user).await {
+            Ok(Some(uid)) => cli.uid = Some(uid),
+            Ok(None) => {
+                eprintln!("No such user: {user}");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("User {user}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(unit) = &cli.unit {
+        match read_unit_pid(unit).await {
+            Ok(pid) if pid.is_empty() => {
+                eprintln!("Unit {unit} is not running");
+                std::process::exit(1);
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("No such unit: {unit}");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Unit {unit}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    let selector = pid_selector(&cli, &process_names);
+    let mut stat_fields = None;
+    if cli.strict {
+        let layout = StatLayout::read().await.unwrap_or_else(|e| {
+            eprintln!("/proc/sys/kernel/osrelease: {e}");
+            std::process::exit(1);
+        });
+        let Some(fields) = layout.fields else {
+            eprintln!(
+                "Unknown layout of `/proc/<pid>/stat` on Linux {release}",
+                release = layout.release
+            );
+            std::process::exit(1);
+        };
+        stat_fields = Some(fields);
+    }
+    let mut components = ComponentOptions {
         cpu: cli.cpu,
+        system_cpu: matches!(cli.cpu_mode, CpuMode::SystemRelative),
+        sched_cpu: cli.cpu_reconcile,
+        wait: true,
+        stack_from_maps: matches!(cli.stack_source, StackSource::Maps),
         mem: cli.mem,
         stack: cli.stack,
         io: cli.io,
         ctx_switch: cli.ctx_switch,
+        signal: cli.signals,
+        oom: cli.oom,
+        start: cli.start_time,
+        addresses: cli.addresses,
+        fd: cli.sockets,
+        taskstats: cli.taskstats,
+        skip_unowned: !cli.read_unowned && !rustix::process::geteuid().is_root(),
+        stat_fields,
+    };
+    if cli.safe_mode {
+        let privileged = [
+            (components.stack, "`stack`"),
+            (components.io, "`io`"),
+            (components.fd, "`sockets`"),
+            (components.sched_cpu, "`cpu-reconcile`"),
+            (components.taskstats, "`taskstats`"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect::<Vec<_>>();
+        if !privileged.is_empty() {
+            warn!(
+                "Safe mode leaves out {components}, which read privileged files",
+                components = privileged.join(", ")
+            );
+        }
+        components = components.safe();
+    }
+    let task = TaskOptions {
+        enabled: cli.task,
+        max: cli.max_tasks,
+    };
+    let display_options = DisplayOptions {
+        change: ChangeMode::PerSecond,
+        number_format: cli.number_format,
+        number_align: cli.align,
+        header: true,
+        merge_threads: cli.merge_threads,
+        thread_summary: cli.thread_summary,
+        mem_delta: cli.mem_delta,
+        children_faults: cli.children_faults,
+        rss_breakdown: cli.rss_breakdown,
+        io_ops: cli.io_ops,
+        highlight_outliers: cli.highlight_outliers,
+        color_by_pid: cli.color_by_pid,
+        blank_components: !cli.no_blank_components,
+        precision: Precision {
+            cpu: cli.cpu_precision.unwrap_or(cli.precision),
+            mem: cli.mem_precision.unwrap_or(cli.precision),
+            io: cli.io_precision.unwrap_or(cli.precision),
+            ctx_switch: cli.ctx_switch_precision.unwrap_or(cli.precision),
+        },
     };
 
+    if let Some(paths) = &cli.diff {
+        if let Err(e) = print_snapshot_diff(&paths[0], &paths[1], display_options) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cli.dry_run {
+        print_matched_processes(selector.as_ref(), cli.color_by_pid).await;
+        return;
+    }
+    if let Some(duration) = cli.duration {
+        if !cli.interval.is_zero() && duration < cli.interval {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::ValueValidation,
+                    format!(
+                        "`--duration` of {duration:?} is shorter than the interval of {:?}",
+                        cli.interval
+                    ),
+                )
+                .exit();
+        }
+    }
+    if cli.since_boot || cli.interval.is_zero() {
+        let mut report = ReportWriter::new(cli.no_output, cli.line_buffered, cli.field_sep.clone());
+        let selector = selector.as_ref();
+        print_since_boot(&mut report, selector, components, task, display_options).await;
+        return;
+    }
+    if cli.diff_mode {
+        let display_options = DisplayOptions {
+            change: ChangeMode::Total,
+            ..display_options
+        };
+        let mut report = ReportWriter::new(cli.no_output, cli.line_buffered, cli.field_sep.clone());
+        let selector = selector.as_ref();
+        print_diff(
+            &cli,
+            &mut report,
+            selector,
+            components,
+            task,
+            display_options,
+        )
+        .await;
+        return;
+    }
+
+    let interval_display_options = DisplayOptions {
+        change: match cli.cumulative {
+            true => ChangeMode::Total,
+            false => ChangeMode::PerSecond,
+        },
+        ..display_options
+    };
+
+    let component_count = [
+        cli.cpu,
+        cli.mem,
+        cli.stack,
+        cli.io,
+        cli.ctx_switch,
+        cli.signals,
+        cli.oom,
+        cli.start_time,
+        cli.addresses,
+        cli.sockets,
+    ]
+    .into_iter()
+    .filter(|c| *c)
+    .count();
+    let mut header_printed = false;
+
     let mut prev_stats = BTreeMap::<usize, TaskGroupStats>::new();
+    // The first sample of each process for `cumulative` and `save-baseline`
+    let mut first_stats = BTreeMap::<usize, TaskGroupStats>::new();
+    // Baselines of the running Average; reset on every Average report
+    let mut average_baseline = BTreeMap::<usize, TaskGroupStats>::new();
+    // Baselines of the rates over the window of `print-window` or the bucket of `reset-every`
+    let windowed = cli.print_window || cli.reset_every.is_some();
+    let mut window_baseline = BTreeMap::<usize, TaskGroupStats>::new();
+    let mut signal_trigger = SignalTrigger::new(cli.snapshot_on_signal.is_some());
+    let mut leak_watchdog = cli
+        .leak_threshold
+        .map(|mb| LeakWatchdog::new(cli.leak_window, mb * 1024));
+    let thresholds = Thresholds {
+        cpu: cli.cpu_threshold,
+        mem: cli.mem_threshold,
+        io: cli.io_threshold,
+    };
+    let mut alert_debouncer = AlertDebouncer::new();
+    let mut io_summary = (cli.summary && cli.io).then(IoSummary::new);
+    let mut wait_summary = (cli.summary && cli.cpu).then(WaitSummary::new);
+    let mut smoother = cli.smooth_counters.then(CounterSmoother::new);
+    let mut adaptive = cli
+        .adaptive
+        .then(|| AdaptiveInterval::new(cli.interval, cli.min_interval, cli.max_interval));
+    let busy_thresholds = Thresholds {
+        cpu: Some(cli.adaptive_cpu),
+        mem: None,
+        io: Some(cli.adaptive_io),
+    };
+    let saved_baseline = cli.compare_baseline.as_ref().map(|path| {
+        Baseline::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+    let mut graphite = cli.graphite_host.clone().map(GraphiteSink::new);
+    let mut pid_files = cli.output_dir.clone().map(|dir| {
+        PidFiles::new(dir.clone()).unwrap_or_else(|e| {
+            eprintln!("{}: {e}", dir.display());
+            std::process::exit(1);
+        })
+    });
 
-    loop {
-        let pid = match (cli.pid, &cli.process_name) {
-            (None, None) => panic!("Provide either `pid` or `process-name`"),
-            (None, Some(process_name)) => ReadPidOptions { process_name }.read_pid().await,
-            (Some(pid), None) => vec![pid],
-            (Some(pid), Some(process_name)) => ReadPidOptions { process_name }
-                .read_pid()
-                .await
-                .into_iter()
-                .filter(|p| *p == pid)
-                .collect::<Vec<_>>(),
-        };
+    let count = cli.count.unwrap_or(usize::MAX);
+    // The Average of the whole run is printed once it is reached
+    let deadline = cli.duration.map(|duration| Instant::now() + duration);
+    // The baselines of that Average
+    let mut run_baseline = BTreeMap::<usize, TaskGroupStats>::new();
+    // The rates of each command averaged over the run, for `save-baseline`; kept after its
+    // processes exit
+    let mut baseline_records = BTreeMap::<String, SampleRecord>::new();
+    // The first sample, for `relative-time`
+    let mut relative_to = None;
+    let mut report = ReportWriter::new(cli.no_output, cli.line_buffered, cli.field_sep.clone());
+    if let (true, OutputFormat::Table) = (cli.metadata_header, cli.format) {
+        match CaptureMetadata::read(cli.interval).await {
+            Ok(metadata) => report.comment(&metadata),
+            Err(e) => eprintln!("Capture metadata: {e}"),
+        }
+    }
+    for iteration in 0..count {
+        let pid = selector.resolve().await;
+
+        // Forget processes that are no longer monitored so that the baselines do not grow with process churn
+        let monitored = pid.iter().copied().collect::<BTreeSet<usize>>();
+        forget_unmonitored(
+            &monitored,
+            &mut [
+                &mut prev_stats,
+                &mut first_stats,
+                &mut average_baseline,
+                &mut window_baseline,
+                &mut run_baseline,
+            ],
+        );
+        if let Some(leak_watchdog) = &mut leak_watchdog {
+            leak_watchdog.retain(|p| monitored.contains(&p));
+        }
+        alert_debouncer.retain(|p| monitored.contains(&p));
+        if let Some(smoother) = &mut smoother {
+            smoother.retain(|p| monitored.contains(&p));
+        }
+        if let Some(pid_files) = &mut pid_files {
+            pid_files.retain(|p| monitored.contains(&p));
+        }
 
+        // Tables of multiple components would be ambiguous without their headers
+        let compact = component_count == 1
+            && !cli.no_compact
+            && (cli.compact || (pid.len() == 1 && std::io::stdout().is_terminal()));
         for &p in &pid {
             if let btree_map::Entry::Vacant(e) = prev_stats.entry(p) {
-                let Ok(s) = read_task_group_stats(p, components, cli.task).await else {
-                    continue;
+                let mut s = match read_task_group_stats(p, components, task).await {
+                    Ok(s) => s,
+                    Err(e) => {
+                        check_strict(cli.strict, p, &e);
+                        continue;
+                    }
                 };
+                if let Some(smoother) = &mut smoother {
+                    smoother.smooth(&mut s);
+                }
+                if let (true, None) = (cli.relative_time, relative_to) {
+                    relative_to = s.process.components.time();
+                }
+                let header = !compact || !header_printed;
+                match cli.first_sample {
+                    FirstSample::Skip => {}
+                    FirstSample::Zero => {
+                        let display = TaskGroupStatsDisplay {
+                            prev_stats: &s,
+                            curr_stats: &s,
+                            options: DisplayOptions {
+                                header,
+                                ..interval_display_options
+                            },
+                        };
+                        report.write(&display);
+                        header_printed = true;
+                    }
+                    FirstSample::Absolute => {
+                        let display = TaskGroupStatsDisplay {
+                            prev_stats: &s.since_start(),
+                            curr_stats: &s,
+                            options: DisplayOptions {
+                                change: ChangeMode::Total,
+                                header,
+                                ..interval_display_options
+                            },
+                        };
+                        report.write(&display);
+                        header_printed = true;
+                    }
+                }
+                average_baseline.insert(p, s.clone());
+                if windowed {
+                    window_baseline.insert(p, s.clone());
+                }
+                if deadline.is_some() {
+                    run_baseline.insert(p, s.clone());
+                }
+                if cli.cumulative || cli.save_baseline.is_some() {
+                    first_stats.insert(p, s.clone());
+                }
                 e.insert(s);
             }
         }
-        tokio::time::sleep(Duration::from_secs(cli.interval)).await;
+        report.flush();
+        let interval = match &adaptive {
+            Some(adaptive) => adaptive.current(),
+            None => cli.interval,
+        };
+        let requests = signal_trigger.sleep(interval).await;
+        // Stop at the sample closest to the deadline
+        let finished = deadline.is_some_and(|deadline| deadline <= Instant::now() + interval / 2);
+        let sampling_start = Instant::now();
+        let mut samples = 0;
+        let mut graphite_batch = String::new();
+        #[cfg(feature = "msgpack")]
+        let mut msgpack_batch = vec![];
+        // Whether any process was busy for `adaptive`
+        let mut busy = false;
+        // The seeds were read in full
+        let refresh = (iteration + 1) % cli.slow_poll.get() == 0;
+        // Whether the samples are reported, for `print-every`
+        let print = (iteration + 1) % cli.print_every.get() == 0;
+        // Whether the window of `print-window` or the bucket of `reset-every` ends here
+        let window_end = match cli.reset_every {
+            Some(reset_every) => (iteration + 1) % reset_every.get() == 0,
+            None => print,
+        };
+        let interval_components = match refresh {
+            true => components,
+            false => components.without_absolute(),
+        };
         for &p in &pid {
-            let Ok(stats) = read_task_group_stats(p, components, cli.task).await else {
-                prev_stats.remove(&p);
+            // Without a baseline, as when the first read failed, the process is seeded again on
+            // the next interval
+            let Some(prev) = prev_stats.get(&p) else {
                 continue;
             };
-            let display = TaskGroupStatsDisplay {
-                prev_stats: prev_stats.get(&p).unwrap(),
-                curr_stats: &stats,
+            let mut stats = match read_task_group_stats(p, interval_components, task).await {
+                Ok(stats) => stats,
+                Err(e) => {
+                    check_strict(cli.strict, p, &e);
+                    debug!("PID {p} is gone: {e}");
+                    // The file stays open for as long as the PID is monitored so that its
+                    // errors follow its samples
+                    if let Some(pid_files) = &mut pid_files {
+                        let command = Some(prev.process.id.command.as_str());
+                        let event = EventRecord::error(p, &e, SystemTime::now());
+                        if let Err(e) = pid_files.write_event(p, command, &event) {
+                            eprintln!("PID {p}: {e}");
+                        }
+                    }
+                    prev_stats.remove(&p);
+                    first_stats.remove(&p);
+                    average_baseline.remove(&p);
+                    window_baseline.remove(&p);
+                    run_baseline.remove(&p);
+                    if let Some(leak_watchdog) = &mut leak_watchdog {
+                        leak_watchdog.forget(p);
+                    }
+                    if let Some(smoother) = &mut smoother {
+                        smoother.forget(p);
+                    }
+                    continue;
+                }
+            };
+            samples += 1;
+            if !refresh {
+                stats.reuse_absolute(prev);
+            }
+            if let Some(smoother) = &mut smoother {
+                smoother.smooth(&mut stats);
+            }
+            if let (Some(leak_watchdog), Some(mem)) =
+                (&mut leak_watchdog, &stats.process.components.mem)
+            {
+                if let Some(growth) = leak_watchdog.observe(p, mem.rss) {
+                    warn!(
+                        "Possible memory leak: PID {p} ({command}) RSS grew by {growth} kB over {window} intervals",
+                        command = stats.process.id.command,
+                        window = cli.leak_window,
+                    );
+                }
+            }
+            if let Some(io_summary) = &mut io_summary {
+                let prev = &prev.process.components.io;
+                if let (Some(prev), Some(curr)) = (prev, &stats.process.components.io) {
+                    io_summary.add(prev, curr);
+                }
+            }
+            if let Some(wait_summary) = &mut wait_summary {
+                let prev = &prev.process.components.cpu;
+                if let (Some(prev), Some(curr)) = (prev, &stats.process.components.cpu) {
+                    wait_summary.add(&stats.process.id, prev, curr);
+                }
+            }
+            if adaptive.is_some() {
+                busy |= busy_thresholds
+                    .check(&prev.process, &stats.process)
+                    .iter()
+                    .any(|(_, _, exceeded)| *exceeded);
+            }
+            if !thresholds.is_empty() {
+                for (metric, value, exceeded) in thresholds.check(&prev.process, &stats.process) {
+                    if alert_debouncer.update(p, metric, exceeded) {
+                        alert(&cli, &stats.process, metric, value);
+                    }
+                }
+            }
+            if cli.jitter {
+                let prev = prev.process.components.time();
+                let curr = stats.process.components.time();
+                if let (Some(prev), Some(curr)) = (prev, curr) {
+                    let display = SampleIntervalDisplay {
+                        tid: match cli.task {
+                            true => TidDisplayOption::Tid,
+                            false => TidDisplayOption::Pid,
+                        },
+                        id: &stats.process.id,
+                        elapsed: curr - prev,
+                        interval,
+                        pid_color: cli.color_by_pid,
+                    };
+                    report.write(&display);
+                }
+            }
+            // A rename is marked once against the previous sample rather than on every row
+            // compared with an older baseline
+            if let (OutputFormat::Table, None) = (cli.format, &saved_baseline) {
+                let display = TaskGroupCommandChangeDisplay {
+                    prev_stats: prev,
+                    curr_stats: &stats,
+                    pid_color: cli.color_by_pid,
+                };
+                report.write(&display);
+            }
+            let baseline = match (cli.cumulative, windowed) {
+                (true, _) => first_stats.get(&p),
+                (false, true) => window_baseline.get(&p),
+                (false, false) => Some(prev),
             };
-            print!("{display}");
+            let Some(baseline) = baseline else {
+                continue;
+            };
+            let records = || {
+                task_group_records(
+                    baseline,
+                    &stats,
+                    interval_display_options.change,
+                    record_time(&stats, relative_to),
+                )
+                .into_iter()
+                .map(|record| record.with_sample(iteration as u64, interval))
+                .collect::<Vec<_>>()
+            };
+            let header = !compact || !header_printed;
+            // Whether the rows are left out for `on-state-change`
+            let unchanged = cli.on_state_change && prev.process.id.state == stats.process.id.state;
+            match (cli.format, &saved_baseline) {
+                _ if !print => {}
+                (OutputFormat::Graphite, _) => {
+                    let records = records();
+                    write_graphite(&mut graphite_batch, &records).unwrap();
+                }
+                #[cfg(feature = "msgpack")]
+                (OutputFormat::Msgpack, _) => {
+                    let records = records();
+                    write_msgpack(&mut msgpack_batch, &records).unwrap();
+                }
+                (OutputFormat::Table, Some(saved_baseline)) => {
+                    let record = SampleRecord::new(
+                        &prev.process,
+                        &stats.process,
+                        ChangeMode::PerSecond,
+                        SystemTime::now(),
+                    );
+                    let tid = TidDisplayOption::Pid;
+                    if header {
+                        report.write(&BaselineComparisonHeaderDisplay { tid });
+                    }
+                    if let Some(baseline) = saved_baseline.get(&stats.process.id.command) {
+                        let display = BaselineComparisonValueDisplay {
+                            tid,
+                            id: &stats.process.id,
+                            curr: &record,
+                            baseline,
+                            pid_color: cli.color_by_pid,
+                        };
+                        report.write(&display);
+                    }
+                }
+                (OutputFormat::Table, None) if unchanged => {}
+                (OutputFormat::Table, None) => {
+                    if cli.on_state_change {
+                        let display = StateChangeDisplay {
+                            tid: match cli.task {
+                                true => TidDisplayOption::Tid,
+                                false => TidDisplayOption::Pid,
+                            },
+                            prev: &prev.process.id,
+                            curr: &stats.process.id,
+                            pid_color: cli.color_by_pid,
+                        };
+                        report.write(&display);
+                    }
+                    let display = TaskGroupStatsDisplay {
+                        prev_stats: baseline,
+                        curr_stats: &stats,
+                        options: DisplayOptions {
+                            header,
+                            ..interval_display_options
+                        },
+                    };
+                    report.write(&display);
+                }
+            }
+            header_printed |= print && !unchanged;
+            if let (true, Some(first)) = (cli.save_baseline.is_some(), first_stats.get(&p)) {
+                let record = SampleRecord::new(
+                    &first.process,
+                    &stats.process,
+                    ChangeMode::PerSecond,
+                    SystemTime::now(),
+                );
+                baseline_records.insert(stats.process.id.command.clone(), record);
+            }
+            if let Some(pid_files) = &mut pid_files {
+                let records = records();
+                if let Err(e) = pid_files.write(p, &stats.process.id.command, &records) {
+                    eprintln!("PID {p}: {e}");
+                }
+            }
+            if windowed && window_end {
+                window_baseline.insert(p, stats.clone());
+            }
             prev_stats.insert(p, stats);
         }
+        if let Some(adaptive) = &mut adaptive {
+            let next = adaptive.next(busy);
+            if next != interval {
+                debug!("Interval changed to {next:?}");
+            }
+        }
+        #[cfg(feature = "msgpack")]
+        if !cli.no_output && !msgpack_batch.is_empty() {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&msgpack_batch).unwrap();
+            stdout.flush().unwrap();
+        }
+        if let (Some(graphite), false) = (&mut graphite, graphite_batch.is_empty()) {
+            if let Err(e) = graphite.send(&graphite_batch).await {
+                eprintln!("Graphite: {e}");
+            }
+        }
+        if let Some(path) = &cli.save_baseline {
+            if let Err(e) = Baseline::save(path, baseline_records.values()) {
+                eprintln!("{}: {e}", path.display());
+            }
+        }
+        if cli.self_stats {
+            let elapsed = sampling_start.elapsed();
+            eprintln!(
+                "Sampled {samples} processes in {elapsed:?} ({rate:.2} samples/s)",
+                rate = samples as f64 / elapsed.as_secs_f64(),
+            );
+        }
+
+        if let (true, Some(dir)) = (requests.snapshot, &cli.snapshot_on_signal) {
+            match Snapshot::new(prev_stats.clone()).save(dir) {
+                Ok(path) => info!("Snapshot written to {}", path.display()),
+                Err(e) => eprintln!("{}: {e}", dir.display()),
+            }
+        }
+        if requests.average {
+            write_average(&mut report, &average_baseline, &prev_stats, display_options);
+            average_baseline = prev_stats.clone();
+            // The Average table interrupts the compact table
+            header_printed = false;
+        }
+        if finished {
+            write_average(&mut report, &run_baseline, &prev_stats, display_options);
+        }
+
+        report.flush();
+
+        if cli.progress {
+            eprint!("\rsample {n}/{count}", n = iteration + 1);
+            std::io::stderr().flush().unwrap();
+        }
+        if finished {
+            break;
+        }
+    }
+    if cli.progress {
+        eprintln!();
+    }
+    if let Some(io_summary) = &io_summary {
+        report.write(io_summary);
+    }
+    if let Some(wait_summary) = &wait_summary {
+        report.write(wait_summary);
+    }
+    report.flush();
+}
+
+/// Exit on malformed data with `strict` instead of taking the process for gone
+fn check_strict(strict: bool, pid: usize, error: &ReadStatsError) {
+    if let (true, ReadStatsError::Parse(e)) = (strict, error) {
+        eprintln!("PID {pid}: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Write the Average of every process in `stats` since its baseline
+fn write_average(
+    report: &mut ReportWriter,
+    baselines: &BTreeMap<usize, TaskGroupStats>,
+    stats: &BTreeMap<usize, TaskGroupStats>,
+    display_options: DisplayOptions,
+) {
+    report.write(&"Average:\n");
+    for (p, stats) in stats {
+        let Some(baseline) = baselines.get(p) else {
+            continue;
+        };
+        let display = TaskGroupStatsDisplay {
+            prev_stats: baseline,
+            curr_stats: stats,
+            options: display_options,
+        };
+        report.write(&display);
+    }
+}
+
+/// When the records of `stats` were sampled: now, or the time since `relative_to` counted from the
+/// Unix epoch
+fn record_time(stats: &TaskGroupStats, relative_to: Option<Instant>) -> SystemTime {
+    match (relative_to, stats.process.components.time()) {
+        (Some(relative_to), Some(time)) => {
+            SystemTime::UNIX_EPOCH + time.saturating_duration_since(relative_to)
+        }
+        _ => SystemTime::now(),
+    }
+}
+
+/// Collects the output of an interval to write it to stdout under a single lock
+struct ReportWriter {
+    buffer: String,
+    /// Render the output and throw it away instead
+    discard: bool,
+    /// Flush after every record instead of once per interval
+    line_buffered: bool,
+    /// Separate the columns by this string instead of padding them
+    field_sep: Option<String>,
+}
+impl ReportWriter {
+    pub fn new(discard: bool, line_buffered: bool, field_sep: Option<String>) -> Self {
+        Self {
+            buffer: String::new(),
+            discard,
+            line_buffered,
+            field_sep,
+        }
+    }
+
+    pub fn write(&mut self, display: &impl std::fmt::Display) {
+        match &self.field_sep {
+            Some(field_sep) => {
+                // The alternate form marks where each column starts
+                let text = format!("{display:#}");
+                for line in text.lines() {
+                    let mut fields = line
+                        .split(COLUMN_START)
+                        .map(|field| strip_colors(field).trim().to_string())
+                        .peekable();
+                    // A line of columns starts with a mark
+                    fields.next_if(|field| field.is_empty());
+                    self.buffer
+                        .push_str(&fields.collect::<Vec<_>>().join(field_sep));
+                    self.buffer.push('\n');
+                }
+            }
+            None => write!(self.buffer, "{display}").unwrap(),
+        }
+        if self.line_buffered {
+            self.flush();
+        }
+    }
+
+    /// Write `display` as is, even with `field_sep`
+    pub fn comment(&mut self, display: &impl std::fmt::Display) {
+        write!(self.buffer, "{display}").unwrap();
+        if self.line_buffered {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if !self.discard && !self.buffer.is_empty() {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(self.buffer.as_bytes()).unwrap();
+            stdout.flush().unwrap();
+        }
+        self.buffer.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Graphite,
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CpuMode {
+    Core,
+    SystemRelative,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum StackSource {
+    Smaps,
+    Maps,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum NameMatch {
+    Exact,
+    Substring,
+    Regex,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FirstSample {
+    Skip,
+    Zero,
+    Absolute,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PidSelection {
+    All,
+    Pid(usize),
+}
+impl FromStr for PidSelection {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ALL" => Ok(Self::All),
+            _ => Ok(Self::Pid(s.parse()?)),
+        }
+    }
+}
+
+/// Parse an interval in seconds, which may be fractional, or 0 for `since-boot`
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let seconds = s.parse::<f64>().map_err(|e| format!("`{s}`: {e}"))?;
+    if seconds == 0. {
+        return Ok(Duration::ZERO);
+    }
+    if !seconds.is_finite() || seconds < 0. {
+        return Err(format!("`{s}` is not a number of seconds of at least 0"));
+    }
+    let interval = Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())?;
+    // Values too small for a nanosecond would read as the 0 of `since-boot`
+    if interval.is_zero() {
+        return Err(format!("`{s}` is shorter than a nanosecond"));
+    }
+    Ok(interval)
+}
+
+/// Parse a duration in seconds, or with a unit of `ms`, `s`, `m` or `h`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value
+        .parse::<f64>()
+        .map_err(|e| format!("`{value}`: {e}"))?;
+    let seconds = match unit {
+        "ms" => value / 1000.,
+        "" | "s" => value,
+        "m" => value * 60.,
+        "h" => value * 60. * 60.,
+        _ => return Err(format!("Unknown unit `{unit}`; use `ms`, `s`, `m` or `h`")),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+/// Select the processes to monitor by the options, of which `main` ensures there is at least one
+fn pid_selector<'a>(cli: &Cli, process_names: &'a [NameMatcher]) -> Box<dyn PidSelector + 'a> {
+    let mut selectors: Vec<Box<dyn PidSelector>> = vec![];
+    if let Some(PidSelection::Pid(pid)) = cli.pid {
+        selectors.push(Box::new(Pids(vec![pid])));
+    }
+    if let Some(unit) = &cli.unit {
+        selectors.push(Box::new(Unit(unit.clone())));
+    }
+    // Whether the PIDs have to be checked against `/proc`
+    let filtered = !process_names.is_empty() || cli.uid.is_some() || cli.min_lifetime.is_some();
+    if filtered || selectors.is_empty() {
+        let read_pid = |process_name| -> Box<dyn PidSelector + 'a> {
+            Box::new(ReadPidOptions {
+                process_name,
+                uid: cli.uid,
+                min_lifetime: cli.min_lifetime,
+            })
+        };
+        match process_names {
+            [] => selectors.push(read_pid(None)),
+            [process_name] => selectors.push(read_pid(Some(process_name))),
+            _ => {
+                let names = process_names.iter().map(|name| read_pid(Some(name)));
+                selectors.push(Box::new(AnyOf(names.collect())));
+            }
+        }
+    }
+    let selector = Box::new(AllOf(selectors));
+    let broad = !process_names.is_empty() || matches!(cli.pid, Some(PidSelection::All));
+    let exclude_self = cli.exclude_self || (!cli.include_self && broad);
+    let selector: Box<dyn PidSelector> = match exclude_self {
+        true => {
+            let self_pid = usize::try_from(std::process::id()).expect("self pid");
+            Box::new(Except {
+                selector,
+                excluded: vec![self_pid],
+            })
+        }
+        false => selector,
+    };
+    match cli.no_refresh_match {
+        true => Box::new(Locked::new(selector)),
+        false => selector,
+    }
+}
+
+/// Print the rates of every process between the snapshots at `prev` and `curr`
+fn print_snapshot_diff(
+    prev: &Path,
+    curr: &Path,
+    display_options: DisplayOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let load = |path: &Path| Snapshot::load(path).map_err(|e| format!("{}: {e}", path.display()));
+    let prev_snapshot = load(prev)?;
+    let curr_snapshot = load(curr)?;
+    if curr_snapshot.time <= prev_snapshot.time {
+        return Err(format!("{} is not newer than {}", curr.display(), prev.display()).into());
+    }
+    let now = Instant::now();
+    let prev_stats = prev_snapshot.stamped(now);
+    let curr_stats = curr_snapshot.stamped_after(&prev_snapshot, now);
+    for p in prev_stats.keys().filter(|p| !curr_stats.contains_key(p)) {
+        warn!("PID {p} is only in {}", prev.display());
+    }
+    let mut stdout = std::io::stdout().lock();
+    for (p, curr_stats) in &curr_stats {
+        let Some(prev_stats) = prev_stats.get(p) else {
+            warn!("PID {p} is only in {}", curr.display());
+            continue;
+        };
+        write!(
+            stdout,
+            "{}",
+            render_delta(prev_stats, curr_stats, display_options)
+        )?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Write the samples of the capture at `path` as NDJSON to stdout
+fn replay(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+    let mut stdout = std::io::stdout().lock();
+    for record in read_capture(&bytes)? {
+        serde_json::to_writer(&mut stdout, &record)?;
+        writeln!(stdout)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+async fn print_version(verbose: bool) {
+    println!("pidstat {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+    let capabilities = Capabilities::read().await;
+    let procfs = match capabilities.procfs {
+        true => "mounted",
+        false => "not mounted",
+    };
+    println!("procfs: {procfs}");
+    let delay_accounting = match capabilities.delay_accounting {
+        Some(true) => "enabled",
+        Some(false) => "disabled",
+        None => "unknown",
+    };
+    println!("delay accounting: {delay_accounting}");
+    let taskstats = match cfg!(feature = "taskstats") {
+        true => "built in",
+        false => "not built in",
+    };
+    println!("taskstats: {taskstats}");
+    let schedstat = match capabilities.schedstat {
+        true => "present",
+        false => "missing",
+    };
+    println!("schedstat: {schedstat}");
+    println!("clock ticks/s: {}", capabilities.clock_ticks_per_second);
+    println!("page size: {} B", capabilities.page_size);
+    match capabilities.online_cpus {
+        Some(cpus) => println!("online CPUs: {cpus}"),
+        None => println!("online CPUs: unknown"),
+    }
+}
+
+async fn print_matched_processes(selector: &dyn PidSelector, pid_color: bool) {
+    let tid = TidDisplayOption::Pid;
+    println!("{header}  Command", header = IdHeaderDisplay { tid });
+    let components = ComponentOptions {
+        cpu: false,
+        system_cpu: false,
+        sched_cpu: false,
+        wait: false,
+        stack_from_maps: false,
+        mem: false,
+        stack: false,
+        io: false,
+        ctx_switch: false,
+        signal: false,
+        oom: false,
+        start: false,
+        addresses: false,
+        fd: false,
+        taskstats: false,
+        skip_unowned: false,
+        stat_fields: None,
+    };
+    for pid in selector.resolve().await {
+        let options = ReadStatsOptions {
+            id: ProcId { pid, tid: None },
+            components,
+        };
+        let Ok(stats) = options.read_stats().await else {
+            continue;
+        };
+        let id = IdValueDisplay {
+            process: &stats.id,
+            tid,
+            pid_color,
+        };
+        let command = CommandDisplay {
+            process: &stats.id,
+            pid_color,
+        };
+        println!("{id}{command}");
+    }
+}
+
+/// Print the rates of every process averaged over its lifetime from a single sample
+async fn print_since_boot(
+    report: &mut ReportWriter,
+    selector: &dyn PidSelector,
+    components: ComponentOptions,
+    task: TaskOptions,
+    display_options: DisplayOptions,
+) {
+    for p in selector.resolve().await {
+        let Ok(stats) = read_task_group_stats(p, components, task).await else {
+            continue;
+        };
+        let Ok(start) = stats.at_start().await else {
+            continue;
+        };
+        let display = TaskGroupStatsDisplay {
+            prev_stats: &start,
+            curr_stats: &stats,
+            options: display_options,
+        };
+        report.write(&display);
+    }
+    report.flush();
+}
+
+/// Print the change of every metric between two samples `interval` seconds apart
+async fn print_diff(
+    cli: &Cli,
+    report: &mut ReportWriter,
+    selector: &dyn PidSelector,
+    components: ComponentOptions,
+    task: TaskOptions,
+    display_options: DisplayOptions,
+) {
+    let pid = selector.resolve().await;
+    let mut first_stats = BTreeMap::new();
+    for &p in &pid {
+        let Ok(s) = read_task_group_stats(p, components, task).await else {
+            continue;
+        };
+        first_stats.insert(p, s);
+    }
+    tokio::time::sleep(cli.interval).await;
+    for (p, first_stats) in &first_stats {
+        let Ok(stats) = read_task_group_stats(*p, components, task).await else {
+            continue;
+        };
+        let renames = TaskGroupCommandChangeDisplay {
+            prev_stats: first_stats,
+            curr_stats: &stats,
+            pid_color: display_options.color_by_pid,
+        };
+        let display = TaskGroupStatsDisplay {
+            prev_stats: first_stats,
+            curr_stats: &stats,
+            options: display_options,
+        };
+        report.write(&renames);
+        report.write(&display);
+    }
+    report.flush();
+}
+
+/// Report a process exceeding the threshold of `metric` by running `hook` without waiting for it
+fn alert(cli: &Cli, stats: &Stats, metric: AlertMetric, value: f64) {
+    let pid = stats.id.proc_id.pid;
+    let command = &stats.id.command;
+    if cli.bell {
+        eprint!("\x07");
+        std::io::stderr().flush().unwrap();
+    }
+    #[cfg(feature = "notify")]
+    if cli.notify {
+        let child = tokio::process::Command::new("notify-send")
+            .arg(format!("pidstat: PID {pid} ({command})"))
+            .arg(format!("{metric} {value:.2} exceeds its threshold"))
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                tokio::spawn(async move { child.wait().await });
+            }
+            Err(e) => eprintln!("Failed to run `notify-send`: {e}"),
+        }
+    }
+    let Some(hook) = &cli.on_threshold else {
+        eprintln!("Threshold exceeded: PID {pid} ({command}) {metric} {value:.2}");
+        return;
+    };
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("PIDSTAT_PID", pid.to_string())
+        .env("PIDSTAT_COMMAND", command)
+        .env("PIDSTAT_METRIC", metric.to_string())
+        .env("PIDSTAT_VALUE", format!("{value:.2}"))
+        .spawn();
+    match child {
+        // Reap the child in the background
+        Ok(mut child) => {
+            tokio::spawn(async move { child.wait().await });
+        }
+        Err(e) => eprintln!("Failed to run `{hook}`: {e}"),
+    }
+}
+
+/// Requests made by signals during an interval
+#[derive(Debug, Clone, Copy, Default)]
+struct SignalRequests {
+    /// An Average report of the current phase on `SIGUSR1`
+    average: bool,
+    /// A snapshot of the current stats on `SIGUSR2`
+    snapshot: bool,
+}
+
+/// Listens for the signals of [`SignalRequests`]
+struct SignalTrigger {
+    #[cfg(unix)]
+    average: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    snapshot: Option<tokio::signal::unix::Signal>,
+}
+impl SignalTrigger {
+    /// Only take over `SIGUSR2` if `snapshot`
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub fn new(snapshot: bool) -> Self {
+        Self {
+            #[cfg(unix)]
+            average: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())
+                .expect("SIGUSR1"),
+            #[cfg(unix)]
+            snapshot: snapshot.then(|| {
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2())
+                    .expect("SIGUSR2")
+            }),
+        }
+    }
+
+    /// Sleep for `duration` and return what was requested in the meantime
+    #[cfg(unix)]
+    pub async fn sleep(&mut self, duration: Duration) -> SignalRequests {
+        async fn recv(signal: &mut Option<tokio::signal::unix::Signal>) -> Option<()> {
+            match signal {
+                Some(signal) => signal.recv().await,
+                None => std::future::pending().await,
+            }
+        }
+
+        let sleep = tokio::time::sleep(duration);
+        tokio::pin!(sleep);
+        let mut requests = SignalRequests::default();
+        loop {
+            tokio::select! {
+                () = &mut sleep => return requests,
+                _ = self.average.recv() => requests.average = true,
+                _ = recv(&mut self.snapshot) => requests.snapshot = true,
+            }
+        }
+    }
+
+    /// Sleep for `duration` and return what was requested in the meantime
+    #[cfg(not(unix))]
+    pub async fn sleep(&mut self, duration: Duration) -> SignalRequests {
+        tokio::time::sleep(duration).await;
+        SignalRequests::default()
     }
 }
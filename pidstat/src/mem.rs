@@ -2,39 +2,116 @@ use core::fmt;
 use std::time::Instant;
 
 use common::{
-    change_per_second,
     value::{
-        FloatColorStatsDisplay, FloatDisplayPostfix, MemoryUnit, PercentageColorStatsDisplay,
-        PercentageDisplayLimit, U64ColorStatsDisplay,
+        normal_color, start_column, write_columns, zero_int_stat_color, FloatColorStatsDisplay,
+        FloatDisplayLimit, FloatDisplayPostfix, MemoryUnit, NumberAlign, NumberFormat,
+        PercentageColorStatsDisplay, PercentageDisplayLimit, U64ColorStatsDisplay,
     },
+    ChangeMode,
 };
-use strict_num::PositiveF64;
+use serde::{Deserialize, Serialize};
+use strict_num::{FiniteF64, PositiveF64};
 
-use crate::process::{
-    CommandDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId, TidDisplayOption,
+use crate::{
+    columns::{headers, ColumnHeaderDisplay},
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemStats {
     pub minflt: u64,
     pub majflt: u64,
+    /// The minor faults of the children that were waited for
+    #[serde(default)]
+    pub cminflt: u64,
+    /// The major faults of the children that were waited for
+    #[serde(default)]
+    pub cmajflt: u64,
     /// In kB
     pub vsz: u64,
     /// In kB
     pub rss: u64,
     /// In kB
     pub tot_mem: u64,
+    /// Resident anonymous memory in kB, or `None` if the kernel does not split RSS
+    #[serde(default)]
+    pub rss_anon: Option<u64>,
+    /// Resident file mappings in kB
+    #[serde(default)]
+    pub rss_file: Option<u64>,
+    /// Resident shared memory in kB
+    #[serde(default)]
+    pub rss_shmem: Option<u64>,
+    #[serde(skip, default = "Instant::now")]
     pub time: Instant,
 }
 
 #[derive(Debug, Clone)]
 pub struct MemStatsHeaderDisplay {
     pub tid: TidDisplayOption,
+    pub change: ChangeMode,
+    pub delta: bool,
+    pub children: bool,
+    pub breakdown: bool,
 }
 impl fmt::Display for MemStatsHeaderDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", IdHeaderDisplay { tid: self.tid })?;
-        writeln!(f, "  minflt/s  majflt/s     VSZ     RSS   %MEM  Command")?;
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        let names = headers(&["minflt/s", "majflt/s"], self.change);
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &names,
+                width: 9,
+            },
+        )?;
+        if self.children {
+            let names = headers(&["cminflt/s", "cmajflt/s"], self.change);
+            write_columns(
+                f,
+                &ColumnHeaderDisplay {
+                    names: &names,
+                    width: 9,
+                },
+            )?;
+        }
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["VSZ", "RSS"],
+                width: 7,
+            },
+        )?;
+        if self.breakdown {
+            write_columns(
+                f,
+                &ColumnHeaderDisplay {
+                    names: &["RssAnon", "RssFile", "RssShm"],
+                    width: 7,
+                },
+            )?;
+        }
+        if self.delta {
+            write_columns(
+                f,
+                &ColumnHeaderDisplay {
+                    names: &["dVSZ", "dRSS"],
+                    width: 7,
+                },
+            )?;
+        }
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["%MEM"],
+                width: 6,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
         Ok(())
     }
 }
@@ -42,58 +119,181 @@ impl fmt::Display for MemStatsHeaderDisplay {
 #[derive(Debug, Clone)]
 pub struct MemStatsValueDisplay<'a> {
     pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
     pub id: &'a ProcessId,
     pub prev_stats: &'a MemStats,
     pub curr_stats: &'a MemStats,
+    pub change: ChangeMode,
+    pub number_format: NumberFormat,
+    pub number_align: NumberAlign,
+    /// The decimals of the rates
+    pub decimals: usize,
+    /// Whether to write the change of VSZ and RSS since the previous sample
+    pub delta: bool,
+    /// Whether to write the faults of the children
+    pub children: bool,
+    /// Whether to write RSS split into anonymous, file, and shared memory
+    pub breakdown: bool,
 }
 impl<'a> fmt::Display for MemStatsValueDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let display = IdValueDisplay {
             process: self.id,
             tid: self.tid,
+            pid_color: self.pid_color,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
         let interval = self.curr_stats.time - self.prev_stats.time;
+        // Totals are counts
+        let decimals = match self.change {
+            ChangeMode::PerSecond => self.decimals,
+            ChangeMode::Total => 0,
+        };
 
-        let minflt = change_per_second(
-            self.prev_stats.minflt.into(),
-            self.curr_stats.minflt.into(),
-            interval,
-        )
-        .expect("minflt");
-        let majflt = change_per_second(
-            self.prev_stats.majflt.into(),
-            self.curr_stats.majflt.into(),
-            interval,
-        )
-        .expect("majflt");
+        let minflt = self
+            .change
+            .change(
+                self.prev_stats.minflt.into(),
+                self.curr_stats.minflt.into(),
+                interval,
+            )
+            .expect("minflt");
+        let majflt = self
+            .change
+            .change(
+                self.prev_stats.majflt.into(),
+                self.curr_stats.majflt.into(),
+                interval,
+            )
+            .expect("majflt");
+        let display = FloatColorStatsDisplay {
+            values: &[minflt],
+            width: 9,
+            postfix: FloatDisplayPostfix::Decimals(decimals),
+            limit: FloatDisplayLimit::ExtremeLow,
+            format: self.number_format,
+            align: self.number_align,
+        };
+        write_columns(f, &display)?;
+        // Major faults mean paging from disk, so any of them is worth noticing
         let display = FloatColorStatsDisplay {
-            values: &[minflt, majflt],
+            values: &[majflt],
             width: 9,
-            postfix: FloatDisplayPostfix::Decimals(2),
+            postfix: FloatDisplayPostfix::Decimals(decimals),
+            limit: FloatDisplayLimit::ExtremeHigh { extreme: 1.0 },
+            format: self.number_format,
+            align: self.number_align,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
+
+        if self.children {
+            let cminflt = self
+                .change
+                .change(
+                    self.prev_stats.cminflt.into(),
+                    self.curr_stats.cminflt.into(),
+                    interval,
+                )
+                .expect("cminflt");
+            let cmajflt = self
+                .change
+                .change(
+                    self.prev_stats.cmajflt.into(),
+                    self.curr_stats.cmajflt.into(),
+                    interval,
+                )
+                .expect("cmajflt");
+            let display = FloatColorStatsDisplay {
+                values: &[cminflt],
+                width: 9,
+                postfix: FloatDisplayPostfix::Decimals(decimals),
+                limit: FloatDisplayLimit::ExtremeLow,
+                format: self.number_format,
+                align: self.number_align,
+            };
+            write_columns(f, &display)?;
+            let display = FloatColorStatsDisplay {
+                values: &[cmajflt],
+                width: 9,
+                postfix: FloatDisplayPostfix::Decimals(decimals),
+                limit: FloatDisplayLimit::ExtremeHigh { extreme: 1.0 },
+                format: self.number_format,
+                align: self.number_align,
+            };
+            write_columns(f, &display)?;
+        }
 
         let display = U64ColorStatsDisplay {
             values: &[self.curr_stats.vsz, self.curr_stats.rss],
             width: 7,
             unit: Some(MemoryUnit::Kilobytes),
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
+
+        if self.breakdown {
+            let curr = &self.curr_stats;
+            for value in [curr.rss_anon, curr.rss_file, curr.rss_shmem] {
+                match value {
+                    Some(value) => {
+                        let display = U64ColorStatsDisplay {
+                            values: &[value],
+                            width: 7,
+                            unit: Some(MemoryUnit::Kilobytes),
+                        };
+                        write_columns(f, &display)?;
+                    }
+                    None => {
+                        start_column(f)?;
+                        write!(
+                            f,
+                            "{start} {dash:>7}{end}",
+                            start = zero_int_stat_color(),
+                            dash = "-",
+                            end = normal_color()
+                        )?
+                    }
+                }
+            }
+        }
+
+        if self.delta {
+            let delta = |prev: u64, curr: u64| {
+                FiniteF64::new(curr as f64 - prev as f64).expect("mem delta")
+            };
+            let display = FloatColorStatsDisplay {
+                values: &[
+                    delta(self.prev_stats.vsz, self.curr_stats.vsz),
+                    delta(self.prev_stats.rss, self.curr_stats.rss),
+                ],
+                width: 7,
+                postfix: FloatDisplayPostfix::Unit(MemoryUnit::Kilobytes),
+                limit: FloatDisplayLimit::Signed,
+                format: self.number_format,
+                align: self.number_align,
+            };
+            write_columns(f, &display)?;
+        }
 
         let mem = PositiveF64::new(self.curr_stats.rss as f64 / self.curr_stats.tot_mem as f64)
             .expect("mem");
         let display = PercentageColorStatsDisplay {
             values: &[mem],
             width: 6,
-            decimals: 2,
+            decimals: self.decimals,
             limit: PercentageDisplayLimit::ExtremeHigh,
+            format: self.number_format,
+            align: self.number_align,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
-        let display = CommandDisplay { process: self.id };
-        writeln!(f, "{}", display)?;
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
 
         Ok(())
     }
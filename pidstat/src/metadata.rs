@@ -0,0 +1,54 @@
+use core::fmt;
+use std::{
+    io,
+    time::{Duration, SystemTime},
+};
+
+use time::OffsetDateTime;
+
+/// The context of a capture needed to interpret its numbers later, written as `#` comments
+#[derive(Debug, Clone)]
+pub struct CaptureMetadata {
+    pub host: String,
+    /// The kernel release
+    pub kernel: String,
+    /// The time between samples
+    pub interval: Duration,
+    pub started: SystemTime,
+    /// The clock ticks per second that CPU times are counted in
+    pub clock_ticks: u64,
+}
+impl CaptureMetadata {
+    /// Read the metadata of a capture starting now with samples `interval` apart
+    pub async fn read(interval: Duration) -> io::Result<Self> {
+        let host = tokio::fs::read_to_string("/proc/sys/kernel/hostname").await?;
+        let kernel = tokio::fs::read_to_string("/proc/sys/kernel/osrelease").await?;
+        Ok(Self {
+            host: host.trim_end().to_string(),
+            kernel: kernel.trim_end().to_string(),
+            interval,
+            started: SystemTime::now(),
+            clock_ticks: rustix::param::clock_ticks_per_second(),
+        })
+    }
+}
+impl fmt::Display for CaptureMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let started = OffsetDateTime::from(self.started);
+        writeln!(f, "# host={}", self.host)?;
+        writeln!(f, "# kernel={}", self.kernel)?;
+        writeln!(f, "# interval={}s", self.interval.as_secs_f64())?;
+        writeln!(
+            f,
+            "# started={:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            started.year(),
+            u8::from(started.month()),
+            started.day(),
+            started.hour(),
+            started.minute(),
+            started.second()
+        )?;
+        writeln!(f, "# clock_ticks={}", self.clock_ticks)?;
+        Ok(())
+    }
+}
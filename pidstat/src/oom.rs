@@ -0,0 +1,108 @@
+use core::fmt;
+use std::time::Instant;
+
+use common::value::{
+    int_stat_color, normal_color, start_column, write_columns, zero_int_stat_color,
+    FloatColorStatsDisplay, FloatDisplayLimit, FloatDisplayPostfix, NumberAlign, NumberFormat,
+};
+use serde::{Deserialize, Serialize};
+use strict_num::FiniteF64;
+
+use crate::{
+    columns::ColumnHeaderDisplay,
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OomStats {
+    /// The badness the OOM killer picks its victim by; the highest goes first
+    pub score: u64,
+    /// Added to the badness; from -1000 (never kill) to 1000
+    pub score_adj: i64,
+    #[serde(skip, default = "Instant::now")]
+    pub time: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct OomStatsHeaderDisplay {
+    pub tid: TidDisplayOption,
+}
+impl fmt::Display for OomStatsHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["oom_score"],
+                width: 9,
+            },
+        )?;
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["oom_adj"],
+                width: 7,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OomStatsValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub curr_stats: &'a OomStats,
+    pub number_format: NumberFormat,
+    pub number_align: NumberAlign,
+}
+impl<'a> fmt::Display for OomStatsValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+
+        let score = FiniteF64::new(self.curr_stats.score as f64).unwrap();
+        let display = FloatColorStatsDisplay {
+            values: &[score],
+            width: 9,
+            postfix: FloatDisplayPostfix::Decimals(0),
+            // Half of the maximum badness without adjustment
+            limit: FloatDisplayLimit::ExtremeHigh { extreme: 500.0 },
+            format: self.number_format,
+            align: self.number_align,
+        };
+        write_columns(f, &display)?;
+
+        let start = match self.curr_stats.score_adj {
+            0 => zero_int_stat_color(),
+            _ => int_stat_color(),
+        };
+        start_column(f)?;
+        write!(
+            f,
+            "{start} {value:7}{end}",
+            value = self.curr_stats.score_adj,
+            end = normal_color()
+        )?;
+
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+use crate::record::{EventRecord, SampleRecord};
+
+/// Writes the records of each process as NDJSON to a file of its own
+#[derive(Debug)]
+pub struct PidFiles {
+    dir: PathBuf,
+    files: BTreeMap<usize, BufWriter<File>>,
+}
+impl PidFiles {
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            files: BTreeMap::new(),
+        })
+    }
+
+    /// Append `records` to the file of `pid`.
+    ///
+    /// The file `<pid>-<command>.ndjson` is created, or truncated if it exists, on the first write.
+    pub fn write(&mut self, pid: usize, command: &str, records: &[SampleRecord]) -> io::Result<()> {
+        let file = self.file(pid, Some(command))?;
+        for record in records {
+            serde_json::to_writer(&mut *file, record)?;
+            writeln!(file)?;
+        }
+        file.flush()
+    }
+
+    /// Append `event` to the file of `pid`.
+    ///
+    /// The file is named `<pid>.ndjson` if it is created here without a known `command`.
+    pub fn write_event(
+        &mut self,
+        pid: usize,
+        command: Option<&str>,
+        event: &EventRecord,
+    ) -> io::Result<()> {
+        let file = self.file(pid, command)?;
+        serde_json::to_writer(&mut *file, event)?;
+        writeln!(file)?;
+        file.flush()
+    }
+
+    fn file(&mut self, pid: usize, command: Option<&str>) -> io::Result<&mut BufWriter<File>> {
+        Ok(match self.files.entry(pid) {
+            btree_map::Entry::Occupied(e) => e.into_mut(),
+            btree_map::Entry::Vacant(e) => {
+                let name = match command {
+                    // Commands may contain path separators
+                    Some(command) => format!("{pid}-{}.ndjson", command.replace('/', "_")),
+                    None => format!("{pid}.ndjson"),
+                };
+                e.insert(BufWriter::new(File::create(self.dir.join(name))?))
+            }
+        })
+    }
+
+    /// Close the file of `pid`
+    pub fn forget(&mut self, pid: usize) {
+        self.files.remove(&pid);
+    }
+
+    /// Close the file of every process for which `f` returns `false`
+    pub fn retain(&mut self, mut f: impl FnMut(usize) -> bool) {
+        self.files.retain(|pid, _| f(*pid));
+    }
+}
+
+/// Sends batches of lines to a Graphite (Carbon) plaintext listener, reconnecting after failures
+#[derive(Debug)]
+pub struct GraphiteSink {
+    addr: String,
+    stream: Option<TcpStream>,
+}
+impl GraphiteSink {
+    pub fn new(addr: String) -> Self {
+        Self { addr, stream: None }
+    }
+
+    /// Write `batch` in one go.
+    ///
+    /// The connection is dropped on failure and opened again on the next batch.
+    pub async fn send(&mut self, batch: &str) -> io::Result<()> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => self.stream.insert(TcpStream::connect(&self.addr).await?),
+        };
+        if let Err(e) = stream.write_all(batch.as_bytes()).await {
+            self.stream = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
@@ -1,27 +1,215 @@
 use core::fmt;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime},
+};
 
-use common::value::{int_stat_color, item_name_color, normal_color, zero_int_stat_color};
+use common::value::{
+    int_stat_color, item_name_color, normal_color, pid_color, start_column, write_columns,
+    zero_int_stat_color,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    cpu::CpuStats, ctx_switch::CtxSwitchStats, io::IoStats, mem::MemStats, read::ProcId,
+    addr::AddressStats,
+    columns::ColumnHeaderDisplay,
+    cpu::CpuStats,
+    ctx_switch::CtxSwitchStats,
+    fd::FdStats,
+    io::IoStats,
+    mem::MemStats,
+    oom::OomStats,
+    read::{ProcId, Stats},
+    signal::SignalStats,
     stack::StackStats,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessId {
     pub uid: usize,
     pub proc_id: ProcId,
     pub command: String,
+    /// The state of the task as a single character like `R` or `S`, as in `ps`
+    pub state: char,
+    /// When the task started, read with `start`
+    #[serde(default)]
+    pub start: Option<SystemTime>,
     // pub delay_asum_count: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentStats {
     pub cpu: Option<CpuStats>,
     pub mem: Option<MemStats>,
     pub stack: Option<StackStats>,
     pub io: Option<IoStats>,
     pub ctx_switch: Option<CtxSwitchStats>,
+    pub signal: Option<SignalStats>,
+    pub oom: Option<OomStats>,
+    pub addresses: Option<AddressStats>,
+    pub fd: Option<FdStats>,
+    /// The components left `None` since only the owner of the task or root may read them
+    #[serde(default)]
+    pub restricted: RestrictedComponents,
+}
+impl ComponentStats {
+    /// When the components were sampled
+    pub fn time(&self) -> Option<Instant> {
+        [
+            self.cpu.as_ref().map(|s| s.time),
+            self.mem.as_ref().map(|s| s.time),
+            self.stack.as_ref().map(|s| s.time),
+            self.io.as_ref().map(|s| s.time),
+            self.ctx_switch.as_ref().map(|s| s.time),
+            self.signal.as_ref().map(|s| s.time),
+            self.oom.as_ref().map(|s| s.time),
+            self.addresses.as_ref().map(|s| s.time),
+            self.fd.as_ref().map(|s| s.time),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+    }
+
+    /// The same sample taken at `time`
+    pub fn at(&self, time: Instant) -> Self {
+        let mut stats = self.clone();
+        let times = [
+            stats.cpu.as_mut().map(|s| &mut s.time),
+            stats.mem.as_mut().map(|s| &mut s.time),
+            stats.stack.as_mut().map(|s| &mut s.time),
+            stats.io.as_mut().map(|s| &mut s.time),
+            stats.ctx_switch.as_mut().map(|s| &mut s.time),
+            stats.signal.as_mut().map(|s| &mut s.time),
+            stats.oom.as_mut().map(|s| &mut s.time),
+            stats.addresses.as_mut().map(|s| &mut s.time),
+            stats.fd.as_mut().map(|s| &mut s.time),
+        ];
+        for t in times.into_iter().flatten() {
+            *t = time;
+        }
+        stats
+    }
+
+    /// The same sample with every counter zeroed, as if taken when the task started
+    pub fn since_start(&self) -> Self {
+        Self {
+            cpu: self.cpu.as_ref().map(|s| CpuStats {
+                user_time: 0,
+                system_time: 0,
+                guest_time: 0,
+                wait_time: 0,
+                blkio_delay: 0,
+                sched_time: s.sched_time.map(|_| 0),
+                ..s.clone()
+            }),
+            mem: self.mem.as_ref().map(|s| MemStats {
+                minflt: 0,
+                majflt: 0,
+                cminflt: 0,
+                cmajflt: 0,
+                ..s.clone()
+            }),
+            stack: self.stack.clone(),
+            io: self.io.as_ref().map(|s| IoStats {
+                read_bytes: 0,
+                write_bytes: 0,
+                cancelled_write_bytes: 0,
+                blkio_swapin_delays: 0,
+                syscr: 0,
+                syscw: 0,
+                ..s.clone()
+            }),
+            ctx_switch: self.ctx_switch.as_ref().map(|s| CtxSwitchStats {
+                nvcsw: 0,
+                nivcsw: 0,
+                ..s.clone()
+            }),
+            signal: self.signal.clone(),
+            oom: self.oom.clone(),
+            addresses: self.addresses.clone(),
+            fd: self.fd.clone(),
+            restricted: self.restricted,
+        }
+    }
+
+    /// Add the counters of `other` to those of `self`, keeping the absolute values of `self`
+    pub fn add_counters(&mut self, other: &Self) {
+        if let (Some(s), Some(o)) = (&mut self.cpu, &other.cpu) {
+            s.user_time += o.user_time;
+            s.system_time += o.system_time;
+            s.guest_time += o.guest_time;
+            s.wait_time += o.wait_time;
+            s.blkio_delay += o.blkio_delay;
+            if let (Some(s), Some(o)) = (&mut s.sched_time, o.sched_time) {
+                *s += o;
+            }
+        }
+        if let (Some(s), Some(o)) = (&mut self.mem, &other.mem) {
+            s.minflt += o.minflt;
+            s.majflt += o.majflt;
+            // The faults of the children are of the whole process, so every thread has them all
+            s.cminflt = s.cminflt.max(o.cminflt);
+            s.cmajflt = s.cmajflt.max(o.cmajflt);
+        }
+        if let (Some(s), Some(o)) = (&mut self.io, &other.io) {
+            s.read_bytes += o.read_bytes;
+            s.write_bytes += o.write_bytes;
+            s.cancelled_write_bytes += o.cancelled_write_bytes;
+            s.blkio_swapin_delays += o.blkio_swapin_delays;
+            s.syscr += o.syscr;
+            s.syscw += o.syscw;
+        }
+        if let (Some(s), Some(o)) = (&mut self.ctx_switch, &other.ctx_switch) {
+            s.nvcsw += o.nvcsw;
+            s.nivcsw += o.nivcsw;
+        }
+    }
+}
+
+/// A row of dashes in place of the values of a component that could not be read
+pub struct RestrictedValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    /// The widths of the value columns of the component
+    pub widths: &'a [usize],
+}
+impl<'a> fmt::Display for RestrictedValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        for &width in self.widths {
+            start_column(f)?;
+            write!(
+                f,
+                "{start} {dash:>width$}{end}",
+                start = zero_int_stat_color(),
+                dash = "-",
+                end = normal_color()
+            )?;
+        }
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+/// The components that need the access of the owner of the task, flagged if they were skipped
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RestrictedComponents {
+    pub stack: bool,
+    pub io: bool,
+    pub fd: bool,
 }
 
 pub struct IdHeaderDisplay {
@@ -29,11 +217,16 @@ pub struct IdHeaderDisplay {
 }
 impl fmt::Display for IdHeaderDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "   UID")?;
-        match self.tid {
-            TidDisplayOption::Tid => write!(f, "      TGID       TID"),
-            TidDisplayOption::Pid => write!(f, "       PID"),
-        }?;
+        let names: &[&str] = match self.tid {
+            TidDisplayOption::Tid => &["TGID", "TID"],
+            TidDisplayOption::Pid => &["PID"],
+        };
+        let uid = ColumnHeaderDisplay {
+            names: &["UID"],
+            width: 5,
+        };
+        write_columns(f, &uid)?;
+        write_columns(f, &ColumnHeaderDisplay { names, width: 9 })?;
         Ok(())
     }
 }
@@ -41,21 +234,32 @@ impl fmt::Display for IdHeaderDisplay {
 pub struct IdValueDisplay<'a> {
     pub process: &'a ProcessId,
     pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
 }
 impl<'a> fmt::Display for IdValueDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let start = item_name_color();
+        let start = match self.pid_color {
+            true => pid_color(self.process.proc_id.pid),
+            false => item_name_color(),
+        };
         let end = normal_color();
+        start_column(f)?;
         write!(f, "{start} {uid:5}{end}", uid = self.process.uid)?;
-        write!(f, "{start}")?;
-        match self.tid {
-            TidDisplayOption::Tid => match self.process.proc_id.tid {
-                Some(tid) => write!(f, "         - {tid:9}")?,
-                None => write!(f, " {tgid:9}         -", tgid = self.process.proc_id.pid)?,
-            },
-            TidDisplayOption::Pid => write!(f, " {pid:9}", pid = self.process.proc_id.pid)?,
+        let pid = self.process.proc_id.pid;
+        // The thread rows leave the TGID out and the process rows the TID
+        let ids: &[Option<usize>] = match (self.tid, self.process.proc_id.tid) {
+            (TidDisplayOption::Tid, Some(tid)) => &[None, Some(tid)],
+            (TidDisplayOption::Tid, None) => &[Some(pid), None],
+            (TidDisplayOption::Pid, _) => &[Some(pid)],
+        };
+        for id in ids {
+            start_column(f)?;
+            match id {
+                Some(id) => write!(f, "{start} {id:9}{end}")?,
+                None => write!(f, "{start}         -{end}")?,
+            }
         }
-        write!(f, "{end}")?;
         Ok(())
     }
 }
@@ -68,21 +272,25 @@ pub enum TidDisplayOption {
 
 pub struct CommandDisplay<'a> {
     pub process: &'a ProcessId,
+    /// Whether to color the command by the PID
+    pub pid_color: bool,
 }
 impl<'a> fmt::Display for CommandDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pid_color = self.pid_color.then(|| pid_color(self.process.proc_id.pid));
+        start_column(f)?;
         match self.process.proc_id.tid {
             Some(_) => write!(
                 f,
                 "{start}  |__{value}{end}",
-                start = zero_int_stat_color(),
+                start = pid_color.unwrap_or(zero_int_stat_color()),
                 value = self.process.command,
                 end = normal_color()
             )?,
             None => write!(
                 f,
                 "{start}  {value}{end}",
-                start = int_stat_color(),
+                start = pid_color.unwrap_or(int_stat_color()),
                 value = self.process.command,
                 end = normal_color()
             )?,
@@ -90,3 +298,128 @@ impl<'a> fmt::Display for CommandDisplay<'a> {
         Ok(())
     }
 }
+
+pub struct CommandHeaderDisplay;
+impl fmt::Display for CommandHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        start_column(f)?;
+        write!(f, "  Command")
+    }
+}
+
+/// Marks a task whose command changed between two samples
+pub struct CommandChangeDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub prev: &'a ProcessId,
+    pub curr: &'a ProcessId,
+}
+impl<'a> fmt::Display for CommandChangeDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.curr,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        start_column(f)?;
+        writeln!(
+            f,
+            "{start}  renamed: {prev} -> {curr}{end}",
+            start = int_stat_color(),
+            prev = self.prev.command,
+            curr = self.curr.command,
+            end = normal_color()
+        )?;
+        Ok(())
+    }
+}
+
+/// Marks a task whose state changed between two samples
+pub struct StateChangeDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub prev: &'a ProcessId,
+    pub curr: &'a ProcessId,
+}
+impl<'a> fmt::Display for StateChangeDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.curr,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        start_column(f)?;
+        writeln!(
+            f,
+            "{start}  state: {prev} -> {curr}{end}",
+            start = int_stat_color(),
+            prev = self.prev.state,
+            curr = self.curr.state,
+            end = normal_color()
+        )?;
+        Ok(())
+    }
+}
+
+/// The real time between two samples of a process and how far it is off the requested interval
+pub struct SampleIntervalDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub elapsed: Duration,
+    pub interval: Duration,
+}
+impl<'a> fmt::Display for SampleIntervalDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        let elapsed = self.elapsed.as_secs_f64() * 1000.;
+        let jitter = elapsed - self.interval.as_secs_f64() * 1000.;
+        let start = zero_int_stat_color();
+        let end = normal_color();
+        start_column(f)?;
+        write!(f, "{start}  interval_ms: {elapsed:.3}{end}")?;
+        start_column(f)?;
+        writeln!(f, "{start} jitter_ms: {jitter:+.3}{end}")?;
+        Ok(())
+    }
+}
+
+/// How many threads of a process are in each state, like `D:1 R:2 S:40`
+pub struct ThreadStateSummaryDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub task: &'a BTreeMap<usize, Stats>,
+}
+impl<'a> fmt::Display for ThreadStateSummaryDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut count = BTreeMap::<char, usize>::new();
+        for stats in self.task.values() {
+            *count.entry(stats.id.state).or_default() += 1;
+        }
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        start_column(f)?;
+        write!(f, "{start}  threads:", start = int_stat_color())?;
+        for (state, count) in count {
+            write!(f, " {state}:{count}")?;
+        }
+        writeln!(f, "{end}", end = normal_color())?;
+        Ok(())
+    }
+}
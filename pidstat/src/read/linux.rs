@@ -1,23 +1,51 @@
-use std::{num::NonZeroU32, path::Path, time::Instant};
+use core::fmt;
+use std::{
+    collections::BTreeMap,
+    num::{NonZeroU32, NonZeroU64},
+    os::unix::fs::MetadataExt,
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
+};
 
 use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use tracing::{trace, warn};
 
 use crate::{
+    addr::AddressStats,
+    clock::{Clock, SystemClock},
     cpu::CpuStats,
     ctx_switch::CtxSwitchStats,
+    fd::FdStats,
     io::IoStats,
     mem::MemStats,
-    process::{ComponentStats, ProcessId},
+    oom::OomStats,
+    process::{ComponentStats, ProcessId, RestrictedComponents},
+    signal::SignalStats,
     stack::StackStats,
 };
 
-use super::{ProcId, ReadPidOptions, ReadStatsError, ReadStatsOptions, ReadTidOptions, Stats};
+use super::{
+    taskstats::read_taskstats, Capabilities, ParseError, ProcId, ReadPidOptions, ReadStatsError,
+    ReadStatsOptions, ReadTidOptions, Stats, TaskGroupStats,
+};
 
 impl ReadPidOptions<'_> {
     pub async fn read_pid(&self) -> Vec<usize> {
         let path = Path::new("/proc");
         let mut pid = vec![];
         let mut read_dir = tokio::fs::read_dir(path).await.expect("/proc");
+        let boot_time = match self.min_lifetime {
+            Some(_) => match read_proc_boot_time().await {
+                Ok(boot_time) => Some(boot_time),
+                Err(e) => {
+                    // No process can be told to be old enough
+                    warn!("Selecting no processes for `min-lifetime` without the boot time: {e}");
+                    return pid;
+                }
+            },
+            None => None,
+        };
         while let Some(entry) = read_dir.next_entry().await.expect("/proc") {
             let Ok(p) = entry.file_name().to_string_lossy().parse::<usize>() else {
                 continue;
@@ -25,8 +53,23 @@ impl ReadPidOptions<'_> {
             let Ok(proc_stat) = read_proc_stat(ProcId { pid: p, tid: None }).await else {
                 continue;
             };
-            if !proc_stat.command.contains(self.process_name) {
-                continue;
+            if let Some(process_name) = self.process_name {
+                if !process_name.is_match(&proc_stat.command) {
+                    continue;
+                }
+            }
+            if let (Some(min_lifetime), Some(boot_time)) = (self.min_lifetime, boot_time) {
+                if boot_time.age(proc_stat.starttime) < min_lifetime {
+                    continue;
+                }
+            }
+            if let Some(uid) = self.uid {
+                let Ok(proc_status) = read_proc_status(ProcId { pid: p, tid: None }).await else {
+                    continue;
+                };
+                if proc_status.uid != uid {
+                    continue;
+                }
             }
             pid.push(p);
         }
@@ -34,15 +77,94 @@ impl ReadPidOptions<'_> {
     }
 }
 
+impl TaskGroupStats {
+    /// [`Self::since_start`] taken when each task started, to report the averages over the
+    /// lifetimes of the tasks against.
+    ///
+    /// The threads whose start cannot be read are left out.
+    pub async fn at_start(&self) -> Result<Self, ReadStatsError> {
+        let boot_time = read_proc_boot_time().await?;
+        let start = |id: ProcId| async move {
+            let proc_stat = read_proc_stat(id).await?;
+            let now = Instant::now();
+            // Not before boot, which is about where the clock of `Instant` starts
+            Ok::<_, ReadStatsError>(
+                now.checked_sub(boot_time.age(proc_stat.starttime))
+                    .unwrap_or(now),
+            )
+        };
+        let mut stats = self.since_start();
+        let process_start = start(stats.process.id.proc_id).await?;
+        stats.process.components = stats.process.components.at(process_start);
+        let mut task = BTreeMap::new();
+        for (tid, mut task_stats) in stats.task {
+            let Ok(task_start) = start(task_stats.id.proc_id).await else {
+                continue;
+            };
+            task_stats.components = task_stats.components.at(task_start);
+            task.insert(tid, task_stats);
+        }
+        stats.task = task;
+        Ok(stats)
+    }
+}
+
+impl Capabilities {
+    pub async fn read() -> Self {
+        // An empty mount point in minimal containers and chroots has no `self`
+        let procfs = tokio::fs::try_exists("/proc/self/stat")
+            .await
+            .unwrap_or(false);
+        let delay_accounting = tokio::fs::read_to_string("/proc/sys/kernel/task_delayacct")
+            .await
+            .ok()
+            .map(|text| text.trim() != "0");
+        let schedstat = tokio::fs::try_exists("/proc/self/schedstat")
+            .await
+            .unwrap_or(false);
+        let online_cpus = tokio::fs::read_to_string("/sys/devices/system/cpu/online")
+            .await
+            .ok()
+            .and_then(|text| parse_cpu_list(text.trim()));
+        Self {
+            procfs,
+            delay_accounting,
+            schedstat,
+            clock_ticks_per_second: rustix::param::clock_ticks_per_second(),
+            page_size: rustix::param::page_size(),
+            online_cpus,
+        }
+    }
+}
+
+/// The number of CPUs in a list like `0-3,6`
+fn parse_cpu_list(text: &str) -> Option<usize> {
+    let mut count = 0;
+    for range in text.split(',') {
+        count += match range.split_once('-') {
+            Some((start, end)) => end.parse::<usize>().ok()? - start.parse::<usize>().ok()? + 1,
+            None => {
+                range.parse::<usize>().ok()?;
+                1
+            }
+        };
+    }
+    Some(count)
+}
+
 impl ReadTidOptions {
     pub async fn read_tid(&self) -> Result<Vec<usize>, ReadStatsError> {
         let path = Path::new("/proc").join(self.tgid.to_string()).join("task");
         let mut tid = vec![];
         let mut read_dir = tokio::fs::read_dir(path)
             .await
-            .map_err(ReadStatsError::NoSuchProcess)?;
-        while let Some(entry) = read_dir.next_entry().await.expect("task") {
-            let t = entry.file_name().to_string_lossy().parse().expect("tid");
+            .map_err(ReadStatsError::io)?;
+        while let Some(entry) = read_dir.next_entry().await.map_err(ReadStatsError::io)? {
+            let t = entry
+                .file_name()
+                .to_string_lossy()
+                .parse()
+                .map_err(|e| ParseError::invalid("tid", e))?;
             tid.push(t);
         }
         Ok(tid)
@@ -51,25 +173,89 @@ impl ReadTidOptions {
 
 impl ReadStatsOptions {
     pub async fn read_stats(&self) -> Result<Stats, ReadStatsError> {
-        let now = Instant::now();
+        self.read_stats_with(&SystemClock).await
+    }
+
+    /// Read the stats and stamp them with the time of `clock`
+    pub async fn read_stats_with(&self, clock: &impl Clock) -> Result<Stats, ReadStatsError> {
+        let now = clock.now();
         let proc_stat = read_proc_stat(self.id).await?;
+        if let Some(expected) = self.components.stat_fields {
+            if proc_stat.fields != expected {
+                return Err(ParseError::FieldCount {
+                    file: "stat",
+                    expected,
+                    found: proc_stat.fields,
+                }
+                .into());
+            }
+        }
         let proc_status = read_proc_status(self.id).await?;
+        let command = match self.id.tid {
+            // Thread names may contain anything, including parentheses
+            Some(_) => read_comm(self.id).await?,
+            None => proc_stat.command,
+        };
+        let mut start = None;
+        if self.components.start {
+            start = Some(read_proc_boot_time().await?.start(proc_stat.starttime));
+        }
         let id = ProcessId {
             uid: proc_status.uid,
             proc_id: self.id,
-            command: proc_stat.command,
+            command,
+            state: proc_stat.state.as_char(),
+            start,
+        };
+
+        // Reading the files of the tasks of other users takes the privileges of root
+        let owned = match self.components.skip_unowned {
+            true => read_owner(self.id).await? == rustix::process::geteuid().as_raw(),
+            false => true,
+        };
+        let mut restricted = RestrictedComponents::default();
+
+        let taskstats = match self.components.taskstats {
+            true => read_taskstats(self.id),
+            false => None,
+        };
+        let clock_ticks_per_second = rustix::param::clock_ticks_per_second();
+        let ns_to_ticks = |ns: u64| clock_ticks_per_second * ns / 1_000_000_000;
+        let delayacct_blkio_ticks = match taskstats {
+            Some(taskstats) => {
+                ns_to_ticks(taskstats.blkio_delay_total + taskstats.swapin_delay_total)
+            }
+            None => proc_stat.delayacct_blkio_ticks,
         };
 
         let mut cpu = None;
         if self.components.cpu {
-            let clock_ticks_per_second = rustix::param::clock_ticks_per_second();
-            let proc_sched = read_proc_sched(self.id).await?;
-            let wait_time = clock_ticks_per_second * proc_sched.wait_time / 1_000_000_000;
+            let mut sched = None;
+            if (taskstats.is_none() && self.components.wait) || self.components.sched_cpu {
+                sched = Some(read_proc_sched(self.id).await?);
+            }
+            let wait_time = match (taskstats, sched) {
+                (Some(taskstats), _) => ns_to_ticks(taskstats.cpu_delay_total),
+                (None, Some(sched)) => ns_to_ticks(sched.wait_time),
+                // Without `wait`
+                (None, None) => 0,
+            };
+            let sched_time = match self.components.sched_cpu {
+                true => sched.map(|sched| ns_to_ticks(sched.cpu_time)),
+                false => None,
+            };
+            let mut system_busy_time = None;
+            if self.components.system_cpu {
+                system_busy_time = Some(read_proc_stat_global().await?.busy());
+            }
             cpu = Some(CpuStats {
                 user_time: proc_stat.utime.saturating_sub(proc_stat.guest_time),
                 system_time: proc_stat.stime,
                 guest_time: proc_stat.guest_time,
                 wait_time,
+                blkio_delay: delayacct_blkio_ticks,
+                system_busy_time,
+                sched_time,
                 time: now,
                 processor: proc_stat.processor,
                 clock_ticks_per_second,
@@ -82,37 +268,97 @@ impl ReadStatsOptions {
             mem = Some(MemStats {
                 minflt: proc_stat.minflt,
                 majflt: proc_stat.majflt,
+                cminflt: proc_stat.cminflt,
+                cmajflt: proc_stat.cmajflt,
                 vsz: proc_stat.vsize / 1024,
                 rss: proc_stat.rss * u64::try_from(page_size).expect("page_size") / 1024,
                 tot_mem: mem_info.mem_total,
+                rss_anon: proc_status.rss_anon,
+                rss_file: proc_status.rss_file,
+                rss_shmem: proc_status.rss_shmem,
                 time: now,
             })
         }
         let mut stack = None;
-        if self.components.stack {
-            let proc_smap = read_proc_smaps(self.id).await?;
+        restricted.stack = self.components.stack && !owned;
+        if self.components.stack && owned {
+            // No stack mapping is no stack
+            let smap = read_proc_smaps(self.id).await?.stack.unwrap_or_default();
+            let stk_size = match self.components.stack_from_maps {
+                true => read_proc_maps_stack(self.id).await?.size,
+                false => smap.size,
+            };
             stack = Some(StackStats {
-                stk_size: proc_smap.stack.size,
-                stk_ref: proc_smap.stack.referenced,
+                stk_size,
+                stk_ref: smap.referenced,
                 time: now,
             });
         }
         let mut io = None;
-        if self.components.io {
+        restricted.io = self.components.io && !owned;
+        if self.components.io && owned {
             let proc_io = read_proc_io(self.id).await?;
             io = Some(IoStats {
                 read_bytes: proc_io.read_bytes,
                 write_bytes: proc_io.write_bytes,
                 cancelled_write_bytes: proc_io.cancelled_write_bytes,
-                blkio_swapin_delays: proc_stat.delayacct_blkio_ticks,
+                syscr: proc_io.syscr,
+                syscw: proc_io.syscw,
+                blkio_swapin_delays: delayacct_blkio_ticks,
                 time: now,
             });
         }
         let mut ctx_switch = None;
         if self.components.ctx_switch {
+            // Prefer the counters of taskstats, which `/proc/<pid>/status` can lag behind
+            let (nvcsw, nivcsw) = match taskstats {
+                Some(taskstats) => (taskstats.nvcsw, taskstats.nivcsw),
+                None => (
+                    proc_status.voluntary_ctxt_switches,
+                    proc_status.nonvoluntary_ctxt_switches,
+                ),
+            };
             ctx_switch = Some(CtxSwitchStats {
-                nvcsw: proc_status.voluntary_ctxt_switches,
-                nivcsw: proc_status.nonvoluntary_ctxt_switches,
+                nvcsw,
+                nivcsw,
+                time: now,
+            });
+        }
+        let mut signal = None;
+        if self.components.signal {
+            signal = Some(SignalStats {
+                blocked: proc_stat.blocked,
+                ignored: proc_stat.sigignore,
+                caught: proc_stat.sigcatch,
+                time: now,
+            });
+        }
+        let mut oom = None;
+        if self.components.oom {
+            let oom_score = read_oom_score(self.id).await?;
+            oom = Some(OomStats {
+                score: oom_score.oom_score,
+                score_adj: oom_score.oom_score_adj,
+                time: now,
+            });
+        }
+        let mut addresses = None;
+        if self.components.addresses {
+            addresses = Some(AddressStats {
+                start_stack: proc_stat.startstack,
+                kstk_esp: proc_stat.kstkesp,
+                kstk_eip: proc_stat.kstkeip,
+                wchan: proc_stat.wchan,
+                time: now,
+            });
+        }
+        let mut fd = None;
+        restricted.fd = self.components.fd && !owned;
+        if self.components.fd && owned {
+            let proc_fd = read_proc_fd(self.id).await?;
+            fd = Some(FdStats {
+                fds: proc_fd.fds,
+                sockets: proc_fd.sockets,
                 time: now,
             });
         }
@@ -122,6 +368,11 @@ impl ReadStatsOptions {
             stack,
             io,
             ctx_switch,
+            signal,
+            oom,
+            addresses,
+            fd,
+            restricted,
         };
 
         Ok(Stats { id, components })
@@ -139,80 +390,96 @@ pub struct ProcStatus {
     pub voluntary_ctxt_switches: u64,
     /// Number of involuntary context switches
     pub nonvoluntary_ctxt_switches: u64,
+    /// Resident anonymous memory in kB, on kernels since 4.5
+    pub rss_anon: Option<u64>,
+    /// Resident file mappings in kB, on kernels since 4.5
+    pub rss_file: Option<u64>,
+    /// Resident shared memory in kB, on kernels since 4.5
+    pub rss_shmem: Option<u64>,
 }
-pub async fn read_proc_status(id: ProcId) -> Result<ProcStatus, ReadStatsError> {
-    let path = id.path("status");
-    let file = tokio::fs::File::options()
-        .read(true)
-        .open(path)
-        .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
-
-    let mut uid = None;
-    let mut threads = None;
-    let mut voluntary_ctxt_switches = None;
-    let mut nonvoluntary_ctxt_switches = None;
-    let buf = tokio::io::BufReader::new(file);
-    let mut lines = buf.lines();
-    while let Some(line) = lines.next_line().await.expect("UTF-8") {
-        const UID: &str = "Uid:";
-        if line.starts_with(UID) {
-            let remaining = line.chars().skip(UID.len()).collect::<String>();
-            uid = Some(
-                remaining
-                    .trim_start()
-                    .split_once('\t')
-                    .expect("uid")
-                    .0
-                    .parse()
-                    .expect("uid"),
-            );
-        }
-        const THREADS: &str = "Threads:";
-        if line.starts_with(THREADS) {
-            threads = Some(
-                line.chars()
-                    .skip(THREADS.len())
-                    .collect::<String>()
-                    .trim_start()
-                    .parse()
-                    .expect("threads"),
-            );
+impl ProcStatus {
+    /// Parse the contents of `/proc/<pid>/status`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut uid = None;
+        let mut threads = None;
+        let mut voluntary_ctxt_switches = None;
+        let mut nonvoluntary_ctxt_switches = None;
+        let mut rss_anon = None;
+        let mut rss_file = None;
+        let mut rss_shmem = None;
+        // The sizes are followed by their unit, which is always kB
+        fn kilobytes(remaining: &str) -> &str {
+            remaining.trim().trim_end_matches("kB").trim_end()
         }
-        const VOLUNTARY_CTXT_SWITCHES: &str = "voluntary_ctxt_switches:";
-        if line.starts_with(VOLUNTARY_CTXT_SWITCHES) {
-            voluntary_ctxt_switches = Some(
-                line.chars()
-                    .skip(VOLUNTARY_CTXT_SWITCHES.len())
-                    .collect::<String>()
-                    .trim_start()
-                    .parse()
-                    .expect("voluntary_ctxt_switches"),
-            );
-        }
-        const NONVOLUNTARY_CTXT_SWITCHES: &str = "nonvoluntary_ctxt_switches:";
-        if line.starts_with(NONVOLUNTARY_CTXT_SWITCHES) {
-            nonvoluntary_ctxt_switches = Some(
-                line.chars()
-                    .skip(NONVOLUNTARY_CTXT_SWITCHES.len())
-                    .collect::<String>()
-                    .trim_start()
-                    .parse()
-                    .expect("nonvoluntary_ctxt_switches"),
-            );
+        for line in text.lines() {
+            const UID: &str = "Uid:";
+            if let Some(remaining) = line.strip_prefix(UID) {
+                // The real UID comes first
+                let real = remaining.split_whitespace().next();
+                uid = Some(parse_field(real, "uid")?);
+            }
+            const THREADS: &str = "Threads:";
+            if let Some(remaining) = line.strip_prefix(THREADS) {
+                threads = Some(parse_field(Some(remaining.trim()), "threads")?);
+            }
+            const VOLUNTARY_CTXT_SWITCHES: &str = "voluntary_ctxt_switches:";
+            if let Some(remaining) = line.strip_prefix(VOLUNTARY_CTXT_SWITCHES) {
+                voluntary_ctxt_switches = Some(parse_field(
+                    Some(remaining.trim()),
+                    "voluntary_ctxt_switches",
+                )?);
+            }
+            const NONVOLUNTARY_CTXT_SWITCHES: &str = "nonvoluntary_ctxt_switches:";
+            if let Some(remaining) = line.strip_prefix(NONVOLUNTARY_CTXT_SWITCHES) {
+                nonvoluntary_ctxt_switches = Some(parse_field(
+                    Some(remaining.trim()),
+                    "nonvoluntary_ctxt_switches",
+                )?);
+            }
+            const RSS_ANON: &str = "RssAnon:";
+            if let Some(remaining) = line.strip_prefix(RSS_ANON) {
+                rss_anon = Some(parse_field(Some(kilobytes(remaining)), "rss_anon")?);
+            }
+            const RSS_FILE: &str = "RssFile:";
+            if let Some(remaining) = line.strip_prefix(RSS_FILE) {
+                rss_file = Some(parse_field(Some(kilobytes(remaining)), "rss_file")?);
+            }
+            const RSS_SHMEM: &str = "RssShmem:";
+            if let Some(remaining) = line.strip_prefix(RSS_SHMEM) {
+                rss_shmem = Some(parse_field(Some(kilobytes(remaining)), "rss_shmem")?);
+            }
         }
+        Ok(ProcStatus {
+            uid: uid.ok_or(ParseError::Missing("uid"))?,
+            threads: threads.ok_or(ParseError::Missing("threads"))?,
+            voluntary_ctxt_switches: voluntary_ctxt_switches
+                .ok_or(ParseError::Missing("voluntary_ctxt_switches"))?,
+            nonvoluntary_ctxt_switches: nonvoluntary_ctxt_switches
+                .ok_or(ParseError::Missing("nonvoluntary_ctxt_switches"))?,
+            rss_anon,
+            rss_file,
+            rss_shmem,
+        })
     }
-    Ok(ProcStatus {
-        uid: uid.expect("uid"),
-        threads: threads.expect("threads"),
-        voluntary_ctxt_switches: voluntary_ctxt_switches.expect("voluntary_ctxt_switches"),
-        nonvoluntary_ctxt_switches: nonvoluntary_ctxt_switches.expect("nonvoluntary_ctxt_switches"),
-    })
+}
+pub async fn read_proc_status(id: ProcId) -> Result<ProcStatus, ReadStatsError> {
+    let path = id.path("status");
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::status", path = %path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
+    let status = ProcStatus::parse(&text)?;
+    trace!(target: "pidstat::read::status", path = %path.display(), ?status);
+    Ok(status)
 }
 
 /// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
 #[derive(Debug, Clone)]
 pub struct ProcStat {
+    /// The number of fields, counting the PID and the command
+    pub fields: usize,
     pub command: String,
     pub state: ProcState,
     /// The PID of the parent of this process
@@ -267,19 +534,31 @@ pub struct ProcStat {
     /// Current soft limit in bytes on the rss of the process; see the description of `RLIMIT_RSS` in `getrlimit(2)`
     pub rsslim: u64,
     /// The address above which program text can run
-    pub startcode: Option<NonZeroU32>,
+    pub startcode: Option<NonZeroU64>,
     /// The address below which program text can run
-    pub endcode: Option<NonZeroU32>,
+    pub endcode: Option<NonZeroU64>,
     /// The address of the start (i.e., bottom) of the stack
-    pub startstack: Option<NonZeroU32>,
+    pub startstack: Option<NonZeroU64>,
     /// The current value of ESP (stack pointer), as found in the kernel stack page for the process
-    pub kstkesp: Option<NonZeroU32>,
+    pub kstkesp: Option<NonZeroU64>,
     /// The current EIP (instruction pointer)
-    pub kstkeip: Option<NonZeroU32>,
+    pub kstkeip: Option<NonZeroU64>,
+    /// The bitmap of pending signals, displayed as a decimal number.
+    /// Obsolete, because it does not provide information on real-time signals; use `/proc/pid/status` instead.
+    pub signal: u64,
+    /// The bitmap of blocked signals, displayed as a decimal number.
+    /// Obsolete, because it does not provide information on real-time signals; use `/proc/pid/status` instead.
+    pub blocked: u64,
+    /// The bitmap of ignored signals, displayed as a decimal number.
+    /// Obsolete, because it does not provide information on real-time signals; use `/proc/pid/status` instead.
+    pub sigignore: u64,
+    /// The bitmap of caught signals, displayed as a decimal number.
+    /// Obsolete, because it does not provide information on real-time signals; use `/proc/pid/status` instead.
+    pub sigcatch: u64,
     /// This is the "channel" in which the process is waiting.
     /// It is the address of a location in the kernel where the process is sleeping.
     /// The corresponding symbolic name can be found in `/proc/pid/wchan`.
-    pub wchan: Option<NonZeroU32>,
+    pub wchan: Option<NonZeroU64>,
     /// Signal to be sent to parent when we die
     pub exit_signal: Option<u32>,
     /// CPU number last executed on
@@ -296,237 +575,244 @@ pub struct ProcStat {
     /// Guest time of the process's children, measured in clock ticks (divide by `sysconf(_SC_CLK_TCK)`)
     pub cguest_time: Option<u64>,
     /// Address above which program initialized and uninitialized (BSS) data are placed
-    pub start_data: Option<NonZeroU32>,
+    pub start_data: Option<NonZeroU64>,
     /// Address below which program initialized and uninitialized (BSS) data are placed
-    pub end_data: Option<NonZeroU32>,
+    pub end_data: Option<NonZeroU64>,
     /// Address above which program heap can be expanded with `brk(2)`
-    pub start_brk: Option<NonZeroU32>,
+    pub start_brk: Option<NonZeroU64>,
     /// Address above which program command-line arguments (`argv`) are placed
-    pub arg_start: Option<NonZeroU32>,
+    pub arg_start: Option<NonZeroU64>,
     /// Address below program command-line arguments (`argv`) are placed
-    pub arg_end: Option<NonZeroU32>,
+    pub arg_end: Option<NonZeroU64>,
     /// Address above which program environment is placed
-    pub env_start: Option<NonZeroU32>,
+    pub env_start: Option<NonZeroU64>,
     /// Address below which program environment is placed
-    pub env_end: Option<NonZeroU32>,
+    pub env_end: Option<NonZeroU64>,
     /// The thread's exit status in the form reported by `waitpid(2)`
     pub exit_code: Option<NonZeroU32>,
 }
+impl ProcStat {
+    /// Parse the contents of `/proc/<pid>/stat`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let command_start = text.find('(').ok_or(ParseError::Missing("command"))? + 1;
+        // Match right-most `)` since the command may contain `)` itself, and no field after it
+        // does
+        let command_end = text.rfind(')').ok_or(ParseError::Missing("command"))?;
+        let command = text
+            .get(command_start..command_end)
+            .ok_or(ParseError::Missing("command"))?
+            .to_string();
+
+        let remaining = text.get(command_end + 2..).unwrap_or_default();
+        let fields = 2 + remaining.trim_end().split(' ').count();
+        let mut items = remaining.split(' ');
+
+        let state = items.next().ok_or(ParseError::Missing("state"))?;
+        let mut chars = state.chars();
+        let state = match (chars.next(), chars.next()) {
+            (Some(c), None) => ProcState::from_char(c),
+            _ => {
+                return Err(ParseError::invalid(
+                    "state",
+                    format!("not a single character: `{state}`"),
+                ))
+            }
+        };
+        let ppid = parse_field::<u32>(items.next(), "ppid")?;
+        let pgrp = parse_field::<u32>(items.next(), "pgrp")?;
+        let session = parse_field::<u32>(items.next(), "session")?;
+        let tty_nr = parse_field::<u32>(items.next(), "tty_nr")?;
+        let tpgid = items
+            .next()
+            .ok_or(ParseError::Missing("tpgid"))?
+            .parse::<u32>()
+            .ok();
+        let flags = parse_field::<u32>(items.next(), "flags")?;
+        let minflt = parse_field::<u64>(items.next(), "minflt")?;
+        let cminflt = parse_field::<u64>(items.next(), "cminflt")?;
+        let majflt = parse_field::<u64>(items.next(), "majflt")?;
+        let cmajflt = parse_field::<u64>(items.next(), "cmajflt")?;
+        let utime = parse_field::<u64>(items.next(), "utime")?;
+        let stime = parse_field::<u64>(items.next(), "stime")?;
+        let cutime = items
+            .next()
+            .ok_or(ParseError::Missing("cutime"))?
+            .parse::<u64>()
+            .ok();
+        let cstime = items
+            .next()
+            .ok_or(ParseError::Missing("cstime"))?
+            .parse::<u64>()
+            .ok();
+        let priority = parse_field::<i64>(items.next(), "priority")?;
+        let nice = parse_field::<i64>(items.next(), "nice")?;
+        let num_threads = parse_field::<u64>(items.next(), "num_threads")?;
+        let _itrealvalue = parse_field::<u64>(items.next(), "itrealvalue")?;
+        let starttime = parse_field::<u64>(items.next(), "starttime")?;
+        let vsize = parse_field::<u64>(items.next(), "vsize")?;
+        let rss = parse_field::<u64>(items.next(), "rss")?;
+        let rsslim = parse_field::<u64>(items.next(), "rsslim")?;
+        let startcode = items
+            .next()
+            .ok_or(ParseError::Missing("startcode"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let endcode = items
+            .next()
+            .ok_or(ParseError::Missing("endcode"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let startstack = items
+            .next()
+            .ok_or(ParseError::Missing("startstack"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let kstkesp = items
+            .next()
+            .ok_or(ParseError::Missing("kstkesp"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let kstkeip = items
+            .next()
+            .ok_or(ParseError::Missing("kstkeip"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let signal = parse_field::<u64>(items.next(), "signal")?;
+        let blocked = parse_field::<u64>(items.next(), "blocked")?;
+        let sigignore = parse_field::<u64>(items.next(), "sigignore")?;
+        let sigcatch = parse_field::<u64>(items.next(), "sigcatch")?;
+        let wchan = items
+            .next()
+            .ok_or(ParseError::Missing("wchan"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let _nswap = parse_field::<u64>(items.next(), "nswap")?;
+        let _cnswap = parse_field::<u64>(items.next(), "cnswap")?;
+        let exit_signal = items
+            .next()
+            .ok_or(ParseError::Missing("exit_signal"))?
+            .parse::<u32>()
+            .ok();
+        let processor = items
+            .next()
+            .ok_or(ParseError::Missing("processor"))?
+            .parse::<u32>()
+            .ok();
+        let rt_priority = parse_field::<u32>(items.next(), "rt_priority")?;
+        let policy = parse_field::<u32>(items.next(), "policy")?;
+        let delayacct_blkio_ticks = parse_field::<u64>(items.next(), "delayacct_blkio_ticks")?;
+        let guest_time = parse_field::<u64>(items.next(), "guest_time")?;
+        let cguest_time = items
+            .next()
+            .ok_or(ParseError::Missing("cguest_time"))?
+            .parse::<u64>()
+            .ok();
+        let start_data = items
+            .next()
+            .ok_or(ParseError::Missing("start_data"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let end_data = items
+            .next()
+            .ok_or(ParseError::Missing("end_data"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let start_brk = items
+            .next()
+            .ok_or(ParseError::Missing("start_brk"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let arg_start = items
+            .next()
+            .ok_or(ParseError::Missing("arg_start"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let arg_end = items
+            .next()
+            .ok_or(ParseError::Missing("arg_end"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let env_start = items
+            .next()
+            .ok_or(ParseError::Missing("env_start"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let env_end = items
+            .next()
+            .ok_or(ParseError::Missing("env_end"))?
+            .parse::<NonZeroU64>()
+            .ok();
+        let exit_code = items
+            .next()
+            .ok_or(ParseError::Missing("exit_code"))?
+            .parse::<NonZeroU32>()
+            .ok();
+
+        Ok(ProcStat {
+            fields,
+            command,
+            state,
+            ppid,
+            pgrp,
+            session,
+            tty_nr,
+            tpgid,
+            flags,
+            minflt,
+            cminflt,
+            majflt,
+            cmajflt,
+            utime,
+            stime,
+            cutime,
+            cstime,
+            priority,
+            nice,
+            num_threads,
+            starttime,
+            vsize,
+            rss,
+            rsslim,
+            startcode,
+            endcode,
+            startstack,
+            kstkesp,
+            kstkeip,
+            signal,
+            blocked,
+            sigignore,
+            sigcatch,
+            wchan,
+            exit_signal,
+            processor,
+            rt_priority,
+            policy,
+            delayacct_blkio_ticks,
+            guest_time,
+            cguest_time,
+            start_data,
+            end_data,
+            start_brk,
+            arg_start,
+            arg_end,
+            env_start,
+            env_end,
+            exit_code,
+        })
+    }
+}
 pub async fn read_proc_stat(id: ProcId) -> Result<ProcStat, ReadStatsError> {
     let path = id.path("stat");
-    let mut file = tokio::fs::File::options()
-        .read(true)
-        .open(path)
+    let text = tokio::fs::read_to_string(&path)
         .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
-    let mut text = String::new();
-    file.read_to_string(&mut text)
-        .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
-
-    let command_start = text.find('(').expect("(") + 1;
-    // Match right-most `)`
-    let command_end = text.len() - text.chars().rev().position(|c| c == ')').expect(")") - 1;
-    let command_len = command_end - command_start;
-    let command = text
-        .chars()
-        .skip(command_start)
-        .take(command_len)
-        .collect::<String>();
-
-    let remaining = text.chars().skip(command_end + 2).collect::<String>();
-    let mut items = remaining.split(' ');
-
-    let state = items.next().expect("state");
-    let state = match state {
-        "R" => ProcState::Running,
-        "S" => ProcState::Sleeping,
-        "D" => ProcState::Waiting,
-        "Z" => ProcState::Zombie,
-        "T" => ProcState::Stopped,
-        "X" => ProcState::Dead,
-        "I" => ProcState::Idle,
-        _ => panic!("unknown state"),
-    };
-    let ppid = items.next().expect("ppid").parse::<u32>().expect("ppid");
-    let pgrp = items.next().expect("pgrp").parse::<u32>().expect("pgrp");
-    let session = items
-        .next()
-        .expect("session")
-        .parse::<u32>()
-        .expect("session");
-    let tty_nr = items
-        .next()
-        .expect("tty_nr")
-        .parse::<u32>()
-        .expect("tty_nr");
-    let tpgid = items.next().expect("tpgid").parse::<u32>().ok();
-    let flags = items.next().expect("flags").parse::<u32>().expect("flags");
-    let minflt = items
-        .next()
-        .expect("minflt")
-        .parse::<u64>()
-        .expect("minflt");
-    let cminflt = items
-        .next()
-        .expect("cminflt")
-        .parse::<u64>()
-        .expect("cminflt");
-    let majflt = items
-        .next()
-        .expect("majflt")
-        .parse::<u64>()
-        .expect("majflt");
-    let cmajflt = items
-        .next()
-        .expect("cmajflt")
-        .parse::<u64>()
-        .expect("cmajflt");
-    let utime = items.next().expect("utime").parse::<u64>().expect("utime");
-    let stime = items.next().expect("stime").parse::<u64>().expect("stime");
-    let cutime = items.next().expect("cutime").parse::<u64>().ok();
-    let cstime = items.next().expect("cstime").parse::<u64>().ok();
-    let priority = items
-        .next()
-        .expect("priority")
-        .parse::<i64>()
-        .expect("priority");
-    let nice = items.next().expect("nice").parse::<i64>().expect("nice");
-    let num_threads = items
-        .next()
-        .expect("num_threads")
-        .parse::<u64>()
-        .expect("num_threads");
-    let _itrealvalue = items
-        .next()
-        .expect("itrealvalue")
-        .parse::<u64>()
-        .expect("itrealvalue");
-    let starttime = items
-        .next()
-        .expect("starttime")
-        .parse::<u64>()
-        .expect("starttime");
-    let vsize = items.next().expect("vsize").parse::<u64>().expect("vsize");
-    let rss = items.next().expect("rss").parse::<u64>().expect("rss");
-    let rsslim = items
-        .next()
-        .expect("rsslim")
-        .parse::<u64>()
-        .expect("rsslim");
-    let startcode = items.next().expect("startcode").parse::<NonZeroU32>().ok();
-    let endcode = items.next().expect("endcode").parse::<NonZeroU32>().ok();
-    let startstack = items.next().expect("startstack").parse::<NonZeroU32>().ok();
-    let kstkesp = items.next().expect("kstkesp").parse::<NonZeroU32>().ok();
-    let kstkeip = items.next().expect("kstkeip").parse::<NonZeroU32>().ok();
-    let _signal = items
-        .next()
-        .expect("signal")
-        .parse::<u64>()
-        .expect("signal");
-    let _blocked = items
-        .next()
-        .expect("blocked")
-        .parse::<u64>()
-        .expect("blocked");
-    let _sigignore = items
-        .next()
-        .expect("sigignore")
-        .parse::<u64>()
-        .expect("sigignore");
-    let _sigcatch = items
-        .next()
-        .expect("sigcatch")
-        .parse::<u64>()
-        .expect("sigcatch");
-    let wchan = items.next().expect("wchan").parse::<NonZeroU32>().ok();
-    let _nswap = items.next().expect("nswap").parse::<u64>().expect("nswap");
-    let _cnswap = items
-        .next()
-        .expect("cnswap")
-        .parse::<u64>()
-        .expect("cnswap");
-    let exit_signal = items.next().expect("exit_signal").parse::<u32>().ok();
-    let processor = items.next().expect("processor").parse::<u32>().ok();
-    let rt_priority = items
-        .next()
-        .expect("rt_priority")
-        .parse::<u32>()
-        .expect("rt_priority");
-    let policy = items
-        .next()
-        .expect("policy")
-        .parse::<u32>()
-        .expect("policy");
-    let delayacct_blkio_ticks = items
-        .next()
-        .expect("delayacct_blkio_ticks")
-        .parse::<u64>()
-        .expect("delayacct_blkio_ticks");
-    let guest_time = items
-        .next()
-        .expect("guest_time")
-        .parse::<u64>()
-        .expect("guest_time");
-    let cguest_time = items.next().expect("cguest_time").parse::<u64>().ok();
-    let start_data = items.next().expect("start_data").parse::<NonZeroU32>().ok();
-    let end_data = items.next().expect("end_data").parse::<NonZeroU32>().ok();
-    let start_brk = items.next().expect("start_brk").parse::<NonZeroU32>().ok();
-    let arg_start = items.next().expect("arg_start").parse::<NonZeroU32>().ok();
-    let arg_end = items.next().expect("arg_end").parse::<NonZeroU32>().ok();
-    let env_start = items.next().expect("env_start").parse::<NonZeroU32>().ok();
-    let env_end = items.next().expect("env_end").parse::<NonZeroU32>().ok();
-    let exit_code = items.next().expect("exit_code").parse::<NonZeroU32>().ok();
-
-    Ok(ProcStat {
-        command,
-        state,
-        ppid,
-        pgrp,
-        session,
-        tty_nr,
-        tpgid,
-        flags,
-        minflt,
-        cminflt,
-        majflt,
-        cmajflt,
-        utime,
-        stime,
-        cutime,
-        cstime,
-        priority,
-        nice,
-        num_threads,
-        starttime,
-        vsize,
-        rss,
-        rsslim,
-        startcode,
-        endcode,
-        startstack,
-        kstkesp,
-        kstkeip,
-        wchan,
-        exit_signal,
-        processor,
-        rt_priority,
-        policy,
-        delayacct_blkio_ticks,
-        guest_time,
-        cguest_time,
-        start_data,
-        end_data,
-        start_brk,
-        arg_start,
-        arg_end,
-        env_start,
-        env_end,
-        exit_code,
-    })
+        .inspect_err(|e| trace!(target: "pidstat::read::stat", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
+    let stat = ProcStat::parse(&text)?;
+    trace!(target: "pidstat::read::stat", path = %path.display(), ?stat);
+    Ok(stat)
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcState {
     Running,
     /// Sleeping in an interruptible wait
@@ -538,7 +824,56 @@ pub enum ProcState {
     Stopped,
     TracingStop,
     Dead,
+    /// Linux 2.6.33 to 3.13 only
+    Wakekill,
+    /// Waking (Linux 2.6.33 to 3.13 only) or paging (before Linux 2.6.0)
+    Waking,
+    /// Linux 3.9 to 3.13 only
+    Parked,
     Idle,
+    /// A state this parser does not know about
+    Unknown(char),
+}
+impl ProcState {
+    pub fn from_char(c: char) -> Self {
+        match c {
+            'R' => ProcState::Running,
+            'S' => ProcState::Sleeping,
+            'D' => ProcState::Waiting,
+            'Z' => ProcState::Zombie,
+            'T' => ProcState::Stopped,
+            't' => ProcState::TracingStop,
+            // `x` from Linux 2.6.33 to 3.13
+            'X' | 'x' => ProcState::Dead,
+            'K' => ProcState::Wakekill,
+            'W' => ProcState::Waking,
+            'P' => ProcState::Parked,
+            'I' => ProcState::Idle,
+            _ => ProcState::Unknown(c),
+        }
+    }
+
+    pub fn as_char(&self) -> char {
+        match self {
+            ProcState::Running => 'R',
+            ProcState::Sleeping => 'S',
+            ProcState::Waiting => 'D',
+            ProcState::Zombie => 'Z',
+            ProcState::Stopped => 'T',
+            ProcState::TracingStop => 't',
+            ProcState::Dead => 'X',
+            ProcState::Wakekill => 'K',
+            ProcState::Waking => 'W',
+            ProcState::Parked => 'P',
+            ProcState::Idle => 'I',
+            ProcState::Unknown(c) => *c,
+        }
+    }
+}
+impl fmt::Display for ProcState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_char())
+    }
 }
 
 /// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
@@ -556,57 +891,61 @@ pub struct ProcIo {
     /// A task can cause "negative" I/O too.
     /// If this task truncates some dirty pagecache, some I/O which another task has been accounted for (in its write_bytes) will not be happening.
     pub cancelled_write_bytes: u64,
+    /// The number of read system calls, whether or not they reached the storage layer
+    pub syscr: u64,
+    /// The number of write system calls, whether or not they reached the storage layer
+    pub syscw: u64,
+}
+impl ProcIo {
+    /// Parse the contents of `/proc/<pid>/io`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut read_bytes = None;
+        let mut write_bytes = None;
+        let mut cancelled_write_bytes = None;
+        let mut syscr = None;
+        let mut syscw = None;
+        for line in text.lines() {
+            const READ_BYTES: &str = "read_bytes: ";
+            if let Some(remaining) = line.strip_prefix(READ_BYTES) {
+                read_bytes = Some(parse_field(Some(remaining), "read_bytes")?);
+            }
+            const WRITE_BYTES: &str = "write_bytes: ";
+            if let Some(remaining) = line.strip_prefix(WRITE_BYTES) {
+                write_bytes = Some(parse_field(Some(remaining), "write_bytes")?);
+            }
+            const CANCELLED_WRITE_BYTES: &str = "cancelled_write_bytes: ";
+            if let Some(remaining) = line.strip_prefix(CANCELLED_WRITE_BYTES) {
+                cancelled_write_bytes =
+                    Some(parse_field(Some(remaining), "cancelled_write_bytes")?);
+            }
+            const SYSCR: &str = "syscr: ";
+            if let Some(remaining) = line.strip_prefix(SYSCR) {
+                syscr = Some(parse_field(Some(remaining), "syscr")?);
+            }
+            const SYSCW: &str = "syscw: ";
+            if let Some(remaining) = line.strip_prefix(SYSCW) {
+                syscw = Some(parse_field(Some(remaining), "syscw")?);
+            }
+        }
+        Ok(ProcIo {
+            read_bytes: read_bytes.ok_or(ParseError::Missing("read_bytes"))?,
+            write_bytes: write_bytes.ok_or(ParseError::Missing("write_bytes"))?,
+            cancelled_write_bytes: cancelled_write_bytes
+                .ok_or(ParseError::Missing("cancelled_write_bytes"))?,
+            syscr: syscr.ok_or(ParseError::Missing("syscr"))?,
+            syscw: syscw.ok_or(ParseError::Missing("syscw"))?,
+        })
+    }
 }
 pub async fn read_proc_io(id: ProcId) -> Result<ProcIo, ReadStatsError> {
     let path = id.path("io");
-    let file = tokio::fs::File::options()
-        .read(true)
-        .open(path)
+    let text = tokio::fs::read_to_string(&path)
         .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
-    let buf = tokio::io::BufReader::new(file);
-    let mut lines = buf.lines();
-    let mut read_bytes = None;
-    let mut write_bytes = None;
-    let mut cancelled_write_bytes = None;
-    while let Some(line) = lines.next_line().await.expect("UTF-8") {
-        const READ_BYTES: &str = "read_bytes: ";
-        if line.starts_with(READ_BYTES) {
-            read_bytes = Some(
-                line.chars()
-                    .skip(READ_BYTES.len())
-                    .collect::<String>()
-                    .parse::<u64>()
-                    .expect("read_bytes"),
-            );
-        }
-        const WRITE_BYTES: &str = "write_bytes: ";
-        if line.starts_with(WRITE_BYTES) {
-            write_bytes = Some(
-                line.chars()
-                    .skip(WRITE_BYTES.len())
-                    .collect::<String>()
-                    .parse::<u64>()
-                    .expect("write_bytes"),
-            );
-        }
-        const CANCELLED_WRITE_BYTES: &str = "cancelled_write_bytes: ";
-        if line.starts_with(CANCELLED_WRITE_BYTES) {
-            cancelled_write_bytes = Some(
-                line.chars()
-                    .skip(CANCELLED_WRITE_BYTES.len())
-                    .collect::<String>()
-                    .parse::<u64>()
-                    .expect("cancelled_write_bytes"),
-            );
-        }
-    }
-    let stats = ProcIo {
-        read_bytes: read_bytes.expect("read_bytes"),
-        write_bytes: write_bytes.expect("write_bytes"),
-        cancelled_write_bytes: cancelled_write_bytes.expect("cancelled_write_bytes"),
-    };
-    Ok(stats)
+        .inspect_err(|e| trace!(target: "pidstat::read::io", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
+    let io = ProcIo::parse(&text)?;
+    trace!(target: "pidstat::read::io", path = %path.display(), ?io);
+    Ok(io)
 }
 
 /// Ref: <https://docs.kernel.org/scheduler/sched-stats.html>
@@ -623,30 +962,136 @@ pub async fn read_proc_sched(id: ProcId) -> Result<ProcSched, ReadStatsError> {
     let path = id.path("schedstat");
     let mut file = tokio::fs::File::options()
         .read(true)
-        .open(path)
+        .open(&path)
         .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::schedstat", path = %path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
     let mut text = String::new();
     file.read_to_string(&mut text)
         .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
+        .map_err(ReadStatsError::io)?;
     drop(file);
 
     let mut items = text.split_whitespace();
 
-    let cpu_time = items.next().expect("cpu_time").parse().expect("cpu_time");
-    let wait_time = items.next().expect("wait_time").parse().expect("wait_time");
-    let timeslices = items
-        .next()
-        .expect("timeslices")
-        .parse()
-        .expect("timeslices");
+    let cpu_time = parse_field(items.next(), "cpu_time")?;
+    let wait_time = parse_field(items.next(), "wait_time")?;
+    let timeslices = parse_field(items.next(), "timeslices")?;
 
-    Ok(ProcSched {
+    let sched = ProcSched {
         cpu_time,
         wait_time,
         timeslices,
-    })
+    };
+    trace!(target: "pidstat::read::schedstat", path = %path.display(), ?sched);
+    Ok(sched)
+}
+
+/// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
+///
+/// The time all CPUs spent in each mode since boot from the `cpu` line of `/proc/stat`, in ticks
+#[derive(Debug, Clone, Copy)]
+pub struct ProcStatGlobal {
+    /// Including guest time
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+}
+impl ProcStatGlobal {
+    /// Parse the contents of `/proc/stat`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let line = text
+            .lines()
+            .find(|line| line.starts_with("cpu "))
+            .ok_or(ParseError::Missing("cpu"))?;
+        let mut items = line.split_whitespace().skip(1);
+        Ok(ProcStatGlobal {
+            user: parse_field(items.next(), "user")?,
+            nice: parse_field(items.next(), "nice")?,
+            system: parse_field(items.next(), "system")?,
+            idle: parse_field(items.next(), "idle")?,
+            iowait: parse_field(items.next(), "iowait")?,
+            irq: parse_field(items.next(), "irq")?,
+            softirq: parse_field(items.next(), "softirq")?,
+            steal: parse_field(items.next(), "steal")?,
+        })
+    }
+
+    /// The time all CPUs spent doing anything but idling
+    pub fn busy(&self) -> u64 {
+        self.user + self.nice + self.system + self.irq + self.softirq + self.steal
+    }
+}
+pub async fn read_proc_stat_global() -> Result<ProcStatGlobal, ReadStatsError> {
+    let path = Path::new("/proc/stat");
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::stat_global", path = %path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
+    let stat = ProcStatGlobal::parse(&text)?;
+    trace!(target: "pidstat::read::stat_global", path = %path.display(), ?stat);
+    Ok(stat)
+}
+
+/// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
+///
+/// The `btime` line of `/proc/stat`
+#[derive(Debug, Clone, Copy)]
+pub struct ProcBootTime {
+    /// When the system booted, in seconds since the Unix epoch
+    pub btime: u64,
+}
+impl ProcBootTime {
+    /// Parse the contents of `/proc/stat`
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let line = text
+            .lines()
+            .find(|line| line.starts_with("btime "))
+            .ok_or(ParseError::Missing("btime"))?;
+        Ok(ProcBootTime {
+            btime: parse_field(line.split_whitespace().nth(1), "btime")?,
+        })
+    }
+
+    /// When a task started `starttime` clock ticks after boot.
+    ///
+    /// `btime` is only precise to the second and shifts along with the wall clock, so the start
+    /// is too.
+    pub fn start(&self, starttime: u64) -> SystemTime {
+        let clock_ticks_per_second = rustix::param::clock_ticks_per_second();
+        SystemTime::UNIX_EPOCH
+            + Duration::from_secs(self.btime)
+            + Duration::from_secs_f64(starttime as f64 / clock_ticks_per_second as f64)
+    }
+
+    /// How long ago a task started `starttime` clock ticks after boot, as precise as
+    /// [`Self::start`]
+    pub fn age(&self, starttime: u64) -> Duration {
+        SystemTime::now()
+            .duration_since(self.start(starttime))
+            .unwrap_or_default()
+    }
+}
+pub async fn read_proc_boot_time() -> Result<ProcBootTime, ReadStatsError> {
+    let path = Path::new("/proc/stat");
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::boot_time", path = %path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
+    let boot_time = ProcBootTime::parse(&text)?;
+    trace!(target: "pidstat::read::boot_time", path = %path.display(), ?boot_time);
+    Ok(boot_time)
 }
 
 /// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
@@ -658,30 +1103,119 @@ pub async fn read_proc_mem_info() -> Result<ProcMemInfo, ReadStatsError> {
     let path = Path::new("/proc/meminfo");
     let file = tokio::fs::File::options()
         .read(true)
-        .open(path)
+        .open(&path)
         .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::meminfo", path = %path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
     let buf = tokio::io::BufReader::new(file);
     let mut lines = buf.lines();
     let mut mem_total = None;
-    while let Some(line) = lines.next_line().await.expect("UTF-8") {
+    while let Some(line) = lines.next_line().await.map_err(ReadStatsError::io)? {
         const MEM_TOTAL: &str = "MemTotal:";
-        if line.starts_with(MEM_TOTAL) {
-            let remaining = line.chars().skip(MEM_TOTAL.len()).collect::<String>();
-            mem_total = Some(
-                remaining
-                    .split_whitespace()
-                    .next()
-                    .expect("mem_total")
-                    .parse()
-                    .expect("mem_total"),
-            );
+        if let Some(remaining) = line.strip_prefix(MEM_TOTAL) {
+            mem_total = Some(parse_field(
+                remaining.split_whitespace().next(),
+                "mem_total",
+            )?);
         }
     }
 
-    Ok(ProcMemInfo {
-        mem_total: mem_total.expect("mem_total"),
-    })
+    let mem_info = ProcMemInfo {
+        mem_total: mem_total.ok_or(ParseError::Missing("mem_total"))?,
+    };
+    trace!(target: "pidstat::read::meminfo", path = %path.display(), ?mem_info);
+    Ok(mem_info)
+}
+
+/// The effective UID of the task, which owns its directory in `/proc`
+pub async fn read_owner(id: ProcId) -> Result<u32, ReadStatsError> {
+    let path = id.path("");
+    let metadata = tokio::fs::metadata(&path)
+        .await
+        .inspect_err(|e| trace!(target: "pidstat::read::owner", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
+    trace!(target: "pidstat::read::owner", path = %path.display(), uid = metadata.uid());
+    Ok(metadata.uid())
+}
+
+/// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
+///
+/// The command name of the task, or the thread name for threads
+pub async fn read_comm(id: ProcId) -> Result<String, ReadStatsError> {
+    let path = id.path("comm");
+    let comm = tokio::fs::read_to_string(&path)
+        .await
+        .inspect_err(|e| trace!(target: "pidstat::read::comm", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
+    let comm = comm.strip_suffix('\n').unwrap_or(&comm).to_string();
+    trace!(target: "pidstat::read::comm", path = %path.display(), ?comm);
+    Ok(comm)
+}
+
+/// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
+#[derive(Debug, Clone, Copy)]
+pub struct ProcFd {
+    /// The entries of `fd`
+    pub fds: u64,
+    /// The entries of `fd` linking to `socket:[inode]`
+    pub sockets: u64,
+}
+pub async fn read_proc_fd(id: ProcId) -> Result<ProcFd, ReadStatsError> {
+    let path = id.path("fd");
+    let mut read_dir = tokio::fs::read_dir(&path)
+        .await
+        .inspect_err(|e| trace!(target: "pidstat::read::fd", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
+    let mut proc_fd = ProcFd { fds: 0, sockets: 0 };
+    while let Some(entry) = read_dir.next_entry().await.map_err(ReadStatsError::io)? {
+        proc_fd.fds += 1;
+        // The file descriptor might have been closed since the directory was listed
+        let Ok(target) = tokio::fs::read_link(entry.path()).await else {
+            continue;
+        };
+        if target
+            .as_os_str()
+            .as_encoded_bytes()
+            .starts_with(b"socket:")
+        {
+            proc_fd.sockets += 1;
+        }
+    }
+    trace!(target: "pidstat::read::fd", path = %path.display(), ?proc_fd);
+    Ok(proc_fd)
+}
+
+/// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
+#[derive(Debug, Clone, Copy)]
+pub struct ProcOomScore {
+    /// The current score that the kernel gives to this process for the purpose of selecting a process for the OOM-killer
+    pub oom_score: u64,
+    /// The adjustment to the badness heuristic used to select which process gets killed in out-of-memory conditions
+    pub oom_score_adj: i64,
+}
+pub async fn read_oom_score(id: ProcId) -> Result<ProcOomScore, ReadStatsError> {
+    let path = id.path("oom_score");
+    let oom_score = tokio::fs::read_to_string(&path)
+        .await
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::oom_score", path = %path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
+    let adj_path = id.path("oom_score_adj");
+    let oom_score_adj = tokio::fs::read_to_string(&adj_path)
+        .await
+        .inspect_err(
+            |e| trace!(target: "pidstat::read::oom_score", path = %adj_path.display(), error = %e),
+        )
+        .map_err(ReadStatsError::io)?;
+    let oom_score = ProcOomScore {
+        oom_score: parse_field(Some(oom_score.trim()), "oom_score")?,
+        oom_score_adj: parse_field(Some(oom_score_adj.trim()), "oom_score_adj")?,
+    };
+    trace!(target: "pidstat::read::oom_score", path = %path.display(), ?oom_score);
+    Ok(oom_score)
 }
 
 /// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
@@ -689,76 +1223,339 @@ pub async fn read_proc_mem_info() -> Result<ProcMemInfo, ReadStatsError> {
 /// Memory consumption for each of the process's mappings
 #[derive(Debug, Clone)]
 pub struct ProcSmaps {
-    pub stack: Smap,
+    /// `None` for tasks without a stack mapping, like kernel threads and zombies
+    pub stack: Option<Smap>,
 }
 pub async fn read_proc_smaps(id: ProcId) -> Result<ProcSmaps, ReadStatsError> {
     let path = id.path("smaps");
     let file = tokio::fs::File::options()
         .read(true)
-        .open(path)
+        .open(&path)
         .await
-        .map_err(ReadStatsError::NoSuchProcess)?;
+        .inspect_err(|e| trace!(target: "pidstat::read::smaps", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
     let buf = tokio::io::BufReader::new(file);
     let mut lines = buf.lines();
     let mut stack = None;
-    while let Some(line) = lines.next_line().await.expect("UTF-8") {
+    while let Some(line) = lines.next_line().await.map_err(ReadStatsError::io)? {
         if line.contains("[stack]") {
-            stack = Some(read_smaps(&mut lines).await);
+            stack = Some(read_smaps(&mut lines).await?);
         }
     }
 
-    Ok(ProcSmaps {
-        stack: stack.expect("stack"),
-    })
+    let smaps = ProcSmaps { stack };
+    trace!(target: "pidstat::read::smaps", path = %path.display(), ?smaps);
+    Ok(smaps)
 }
+
+/// Ref: <https://man7.org/linux/man-pages/man5/proc.5.html>
+///
+/// The stack mappings of `maps`
 #[derive(Debug, Clone, Copy)]
+pub struct ProcMapsStack {
+    /// The total size of the stack mappings in kB
+    pub size: u64,
+}
+/// Sum the stack mappings of the task.
+///
+/// Linux 3.4 to 4.4 label the stack of each thread `[stack:<tid>]`; a thread is given its own
+/// mappings there and the main `[stack]` otherwise, while a process is given all of them.
+pub async fn read_proc_maps_stack(id: ProcId) -> Result<ProcMapsStack, ReadStatsError> {
+    let path = id.path("maps");
+    let maps = tokio::fs::read_to_string(&path)
+        .await
+        .inspect_err(|e| trace!(target: "pidstat::read::maps", path = %path.display(), error = %e))
+        .map_err(ReadStatsError::io)?;
+    let thread_label = id.tid.map(|tid| format!("[stack:{tid}]"));
+    let mut main_size = 0;
+    let mut thread_size = None;
+    let mut total_size = 0;
+    for line in maps.lines() {
+        let mut items = line.split_whitespace();
+        let range = items.next().ok_or(ParseError::Missing("address"))?;
+        let Some(pathname) = items.nth(4) else {
+            continue;
+        };
+        if !pathname.starts_with("[stack") {
+            continue;
+        }
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| ParseError::invalid("address", format!("not a range: `{range}`")))?;
+        let start =
+            u64::from_str_radix(start, 16).map_err(|e| ParseError::invalid("address", e))?;
+        let end = u64::from_str_radix(end, 16).map_err(|e| ParseError::invalid("address", e))?;
+        let size = (end - start) / 1024;
+        total_size += size;
+        if pathname == "[stack]" {
+            main_size += size;
+        }
+        if Some(pathname) == thread_label.as_deref() {
+            *thread_size.get_or_insert(0) += size;
+        }
+    }
+    let size = match id.tid {
+        Some(_) => thread_size.unwrap_or(main_size),
+        None => total_size,
+    };
+    let stack = ProcMapsStack { size };
+    trace!(target: "pidstat::read::maps", path = %path.display(), ?stack);
+    Ok(stack)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Smap {
     /// The size of the mapping in kB
     pub size: u64,
     /// The amount of memory currently marked as referenced or accessed in kB
     pub referenced: u64,
 }
-async fn read_smaps<R>(lines: &mut tokio::io::Lines<R>) -> Smap
+async fn read_smaps<R>(lines: &mut tokio::io::Lines<R>) -> Result<Smap, ReadStatsError>
 where
     R: tokio::io::AsyncBufRead + Unpin,
 {
-    const SIZE: &str = "Size:";
-    let mut size = None;
-    while let Some(line) = lines.next_line().await.expect("UTF-8") {
-        if line.starts_with(SIZE) {
-            size = Some(
-                line.chars()
-                    .skip(SIZE.len())
-                    .collect::<String>()
-                    .split_whitespace()
-                    .next()
-                    .expect("size")
-                    .parse()
-                    .expect("size"),
-            );
-            break;
+    /// Parse the kB of the next line starting with `key`
+    async fn read_kilobytes<R>(
+        lines: &mut tokio::io::Lines<R>,
+        key: &str,
+        field: &'static str,
+    ) -> Result<u64, ReadStatsError>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        while let Some(line) = lines.next_line().await.map_err(ReadStatsError::io)? {
+            if let Some(remaining) = line.strip_prefix(key) {
+                return Ok(parse_field(remaining.split_whitespace().next(), field)?);
+            }
         }
+        Err(ParseError::Missing(field).into())
     }
-    const REFERENCED: &str = "Referenced:";
-    let mut referenced = None;
-    while let Some(line) = lines.next_line().await.expect("UTF-8") {
-        if line.starts_with(REFERENCED) {
-            referenced = Some(
-                line.chars()
-                    .skip(REFERENCED.len())
-                    .collect::<String>()
-                    .split_whitespace()
-                    .next()
-                    .expect("referenced")
-                    .parse()
-                    .expect("referenced"),
-            );
-            break;
+    let size = read_kilobytes(lines, "Size:", "size").await?;
+    let referenced = read_kilobytes(lines, "Referenced:", "referenced").await?;
+    Ok(Smap { size, referenced })
+}
+
+fn parse_field<T>(value: Option<&str>, field: &'static str) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = value.ok_or(ParseError::Missing(field))?;
+    value.parse().map_err(|e| ParseError::invalid(field, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{clock::ManualClock, read::ComponentOptions};
+
+    use super::*;
+
+    /// `/proc/<pid>/stat` of a Linux 6 process with the command `comm`
+    fn stat(comm: &str) -> String {
+        format!(
+            "1234 ({comm}) S 1 1234 1234 34816 1234 4194304 1200 3400 5 7 30 12 40 20 20 0 1 0 \
+            5000 8720384 800 18446744073709551615 94000000000000 94000000900000 140730000000000 \
+            0 0 0 65536 3686404 1266761467 1 0 0 17 3 0 0 2 0 0 94000001000000 94000001040000 \
+            94000002000000 140730000001000 140730000001100 140730000001100 140730000002000 0\n"
+        )
+    }
+
+    #[test]
+    fn parse_stat() {
+        let stat = ProcStat::parse(&stat("bash")).unwrap();
+        assert_eq!(stat.fields, 52);
+        assert_eq!(stat.command, "bash");
+        assert_eq!(stat.state, ProcState::Sleeping);
+        assert_eq!(stat.ppid, 1);
+        assert_eq!((stat.minflt, stat.cminflt), (1200, 3400));
+        assert_eq!((stat.majflt, stat.cmajflt), (5, 7));
+        assert_eq!((stat.utime, stat.stime), (30, 12));
+        assert_eq!((stat.cutime, stat.cstime), (Some(40), Some(20)));
+        assert_eq!(stat.num_threads, 1);
+        assert_eq!(stat.starttime, 5000);
+        assert_eq!((stat.vsize, stat.rss), (8720384, 800));
+        assert_eq!((stat.blocked, stat.sigignore), (65536, 3686404));
+        assert_eq!(stat.processor, Some(3));
+        assert_eq!(stat.delayacct_blkio_ticks, 2);
+        assert_eq!(stat.exit_code, None);
+    }
+
+    #[test]
+    fn parse_stat_missing_fields() {
+        let text = "1234 (bash) S 1 1234 1234";
+        assert!(matches!(
+            ProcStat::parse(text),
+            Err(ParseError::Missing("tty_nr"))
+        ));
+    }
+
+    #[test]
+    fn parse_stat_command_with_parentheses() {
+        for comm in ["foo) bar", "(sd-pam)", "a) S 1 2 (b", "héllo)"] {
+            let stat = ProcStat::parse(&stat(comm)).unwrap();
+            assert_eq!(stat.command, comm);
+            assert_eq!(stat.fields, 52);
+            assert_eq!(stat.state, ProcState::Sleeping);
+            assert_eq!(stat.ppid, 1);
+            assert_eq!(stat.exit_code, None);
+        }
+    }
+
+    #[test]
+    fn parse_stat_addresses() {
+        let stat = ProcStat::parse(&stat("bash")).unwrap();
+        // Above `u32::MAX`, which does not fit a `usize` on 32-bit
+        assert_eq!(stat.startcode.map(NonZeroU64::get), Some(94000000000000));
+        assert_eq!(stat.startstack.map(NonZeroU64::get), Some(140730000000000));
+        assert_eq!(stat.env_end.map(NonZeroU64::get), Some(140730000002000));
+        // Hidden or unset addresses read as 0
+        assert_eq!(stat.kstkesp, None);
+        assert_eq!(stat.kstkeip, None);
+        assert_eq!(stat.wchan.map(NonZeroU64::get), Some(1));
+    }
+
+    #[test]
+    fn proc_state_from_char() {
+        let states = [
+            ('R', ProcState::Running),
+            ('S', ProcState::Sleeping),
+            ('D', ProcState::Waiting),
+            ('Z', ProcState::Zombie),
+            ('T', ProcState::Stopped),
+            ('t', ProcState::TracingStop),
+            ('X', ProcState::Dead),
+            ('x', ProcState::Dead),
+            ('K', ProcState::Wakekill),
+            ('W', ProcState::Waking),
+            ('P', ProcState::Parked),
+            ('I', ProcState::Idle),
+            ('Q', ProcState::Unknown('Q')),
+        ];
+        for (c, state) in states {
+            assert_eq!(ProcState::from_char(c), state, "{c}");
+        }
+        // `x` is written as `X`
+        for c in "RSDZTtXKWPIQ".chars() {
+            assert_eq!(ProcState::from_char(c).as_char(), c);
         }
     }
 
-    Smap {
-        size: size.expect("size"),
-        referenced: referenced.expect("referenced"),
+    #[test]
+    fn parse_status() {
+        let text = "\
+Name:\tbash
+State:\tS (sleeping)
+Uid:\t1000\t1001\t1001\t1001
+Gid:\t1000\t1000\t1000\t1000
+VmRSS:\t    3200 kB
+RssAnon:\t     600 kB
+RssFile:\t    2500 kB
+RssShmem:\t     100 kB
+Threads:\t4
+voluntary_ctxt_switches:\t150
+nonvoluntary_ctxt_switches:\t9
+";
+        let status = ProcStatus::parse(text).unwrap();
+        assert_eq!(status.uid, 1000);
+        assert_eq!(status.threads, 4);
+        assert_eq!(status.voluntary_ctxt_switches, 150);
+        assert_eq!(status.nonvoluntary_ctxt_switches, 9);
+        assert_eq!(status.rss_anon, Some(600));
+        assert_eq!(status.rss_file, Some(2500));
+        assert_eq!(status.rss_shmem, Some(100));
+    }
+
+    #[test]
+    fn parse_status_before_rss_split() {
+        // Kernels before 4.5 do not split RSS
+        let text = "\
+Uid:\t0\t0\t0\t0
+Threads:\t1
+voluntary_ctxt_switches:\t0
+nonvoluntary_ctxt_switches:\t0
+";
+        let status = ProcStatus::parse(text).unwrap();
+        assert_eq!(status.rss_anon, None);
+        assert_eq!(status.rss_file, None);
+        assert_eq!(status.rss_shmem, None);
+    }
+
+    #[test]
+    fn parse_io() {
+        let text = "\
+rchar: 323934931
+wchar: 323929600
+syscr: 632687
+syscw: 632675
+read_bytes: 4096
+write_bytes: 323932160
+cancelled_write_bytes: 1024
+";
+        let io = ProcIo::parse(text).unwrap();
+        assert_eq!(io.read_bytes, 4096);
+        assert_eq!(io.write_bytes, 323932160);
+        assert_eq!(io.cancelled_write_bytes, 1024);
+        assert_eq!((io.syscr, io.syscw), (632687, 632675));
+    }
+
+    #[test]
+    fn parse_io_invalid() {
+        let text = "read_bytes: many\n";
+        assert!(matches!(
+            ProcIo::parse(text),
+            Err(ParseError::Invalid {
+                field: "read_bytes",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn read_stats_with_manual_clock() {
+        let components = ComponentOptions {
+            cpu: true,
+            system_cpu: false,
+            sched_cpu: false,
+            wait: false,
+            mem: true,
+            stack: false,
+            stack_from_maps: false,
+            io: false,
+            ctx_switch: true,
+            signal: false,
+            oom: false,
+            addresses: false,
+            start: false,
+            fd: false,
+            taskstats: false,
+            skip_unowned: false,
+            stat_fields: None,
+        };
+        let options = ReadStatsOptions {
+            id: ProcId {
+                pid: std::process::id() as usize,
+                tid: None,
+            },
+            components,
+        };
+        let clock = ManualClock::new(Instant::now());
+        let prev = options.read_stats_with(&clock).await.unwrap();
+        clock.advance(Duration::from_secs(1));
+        let curr = options.read_stats_with(&clock).await.unwrap();
+        let prev = &prev.components;
+        let curr = &curr.components;
+        // Every component is stamped with the time of the clock, not of the system
+        assert_eq!(prev.time(), Some(clock.now() - Duration::from_secs(1)));
+        assert_eq!(curr.time(), Some(clock.now()));
+        let (prev_cpu, curr_cpu) = (prev.cpu.as_ref().unwrap(), curr.cpu.as_ref().unwrap());
+        assert_eq!(curr_cpu.time - prev_cpu.time, Duration::from_secs(1));
+        let (prev_mem, curr_mem) = (prev.mem.as_ref().unwrap(), curr.mem.as_ref().unwrap());
+        assert_eq!(curr_mem.time - prev_mem.time, Duration::from_secs(1));
+        let prev_ctx_switch = prev.ctx_switch.as_ref().unwrap();
+        let curr_ctx_switch = curr.ctx_switch.as_ref().unwrap();
+        assert_eq!(
+            curr_ctx_switch.time - prev_ctx_switch.time,
+            Duration::from_secs(1)
+        );
     }
 }
@@ -1,19 +1,341 @@
-use super::{ReadPidOptions, ReadStatsError, ReadStatsOptions, ReadTidOptions, Stats};
+use std::{
+    collections::BTreeMap,
+    ffi::{c_int, CStr},
+    io,
+    mem::{self, MaybeUninit},
+    sync::OnceLock,
+    time::{Duration, Instant, SystemTime},
+};
+
+use tracing::trace;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    cpu::CpuStats,
+    io::IoStats,
+    mem::MemStats,
+    process::{ComponentStats, ProcessId, RestrictedComponents},
+};
+
+use super::{
+    Capabilities, ReadPidOptions, ReadStatsError, ReadStatsOptions, ReadTidOptions, Stats,
+    TaskGroupStats,
+};
+
+/// CPU times are kept in nanoseconds, so the ticks of [`CpuStats`] are nanoseconds
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
 
 impl ReadPidOptions<'_> {
     pub async fn read_pid(&self) -> Vec<usize> {
-        todo!()
+        let mut pid = vec![];
+        for p in list_all_pid() {
+            let Ok(p) = usize::try_from(p) else {
+                continue;
+            };
+            if let Some(process_name) = self.process_name {
+                let Some(name) = proc_name(p) else {
+                    continue;
+                };
+                if !process_name.is_match(&name) {
+                    continue;
+                }
+            }
+            if self.min_lifetime.is_some() || self.uid.is_some() {
+                let Ok(bsd_info) = pid_info::<libc::proc_bsdinfo>(p, libc::PROC_PIDTBSDINFO) else {
+                    continue;
+                };
+                if let Some(min_lifetime) = self.min_lifetime {
+                    let start = bsd_start_time(&bsd_info);
+                    let age = SystemTime::now().duration_since(start).unwrap_or_default();
+                    if age < min_lifetime {
+                        continue;
+                    }
+                }
+                if let Some(uid) = self.uid {
+                    if bsd_info.pbi_ruid as usize != uid {
+                        continue;
+                    }
+                }
+            }
+            pid.push(p);
+        }
+        pid
+    }
+}
+
+impl TaskGroupStats {
+    /// [`Self::since_start`] taken when the process started, to report the averages over its
+    /// lifetime against.
+    ///
+    /// There are no threads to start, as they are not read on macOS.
+    pub async fn at_start(&self) -> Result<Self, ReadStatsError> {
+        let bsd_info =
+            pid_info::<libc::proc_bsdinfo>(self.process.id.proc_id.pid, libc::PROC_PIDTBSDINFO)?;
+        let age = SystemTime::now()
+            .duration_since(bsd_start_time(&bsd_info))
+            .unwrap_or_default();
+        let now = Instant::now();
+        // Not before boot, which is about where the clock of `Instant` starts
+        let process_start = now.checked_sub(age).unwrap_or(now);
+        let mut stats = self.since_start();
+        stats.process.components = stats.process.components.at(process_start);
+        stats.task = BTreeMap::new();
+        Ok(stats)
+    }
+}
+
+impl Capabilities {
+    /// There is no procfs, delay accounting nor schedstat to read from
+    pub async fn read() -> Self {
+        let online_cpus = std::thread::available_parallelism().ok().map(|n| n.get());
+        Self {
+            procfs: false,
+            delay_accounting: None,
+            schedstat: false,
+            clock_ticks_per_second: rustix::param::clock_ticks_per_second(),
+            page_size: rustix::param::page_size(),
+            online_cpus,
+        }
     }
 }
 
 impl ReadTidOptions {
     pub async fn read_tid(&self) -> Result<Vec<usize>, ReadStatsError> {
-        todo!()
+        Err(ReadStatsError::Unsupported("Threads"))
     }
 }
 
 impl ReadStatsOptions {
     pub async fn read_stats(&self) -> Result<Stats, ReadStatsError> {
-        todo!()
+        self.read_stats_with(&SystemClock).await
+    }
+
+    /// Read the stats and stamp them with the time of `clock`.
+    ///
+    /// Only `cpu`, `mem` and `io` are read; the other components are left out. Threads are not
+    /// supported.
+    pub async fn read_stats_with(&self, clock: &impl Clock) -> Result<Stats, ReadStatsError> {
+        let now = clock.now();
+        if self.id.tid.is_some() {
+            return Err(ReadStatsError::Unsupported("Threads"));
+        }
+        let pid = self.id.pid;
+        let bsd_info = pid_info::<libc::proc_bsdinfo>(pid, libc::PROC_PIDTBSDINFO)?;
+        let state = match bsd_info.pbi_status {
+            libc::SRUN => 'R',
+            libc::SSLEEP => 'S',
+            libc::SSTOP => 'T',
+            libc::SZOMB => 'Z',
+            _ => '?',
+        };
+        let start = self.components.start.then(|| bsd_start_time(&bsd_info));
+        let id = ProcessId {
+            uid: bsd_info.pbi_ruid as usize,
+            proc_id: self.id,
+            command: c_string(&bsd_info.pbi_comm),
+            state,
+            start,
+        };
+
+        let task_info = match self.components.cpu || self.components.mem {
+            true => Some(pid_info::<libc::proc_taskinfo>(
+                pid,
+                libc::PROC_PIDTASKINFO,
+            )?),
+            false => None,
+        };
+        let mut cpu = None;
+        if let (true, Some(task_info)) = (self.components.cpu, &task_info) {
+            cpu = Some(CpuStats {
+                user_time: mach_time_to_nanos(task_info.pti_total_user),
+                system_time: mach_time_to_nanos(task_info.pti_total_system),
+                guest_time: 0,
+                // The run queue wait is not exposed
+                wait_time: 0,
+                blkio_delay: 0,
+                system_busy_time: None,
+                sched_time: None,
+                time: now,
+                processor: None,
+                clock_ticks_per_second: NANOS_PER_SECOND,
+            });
+        }
+        let mut mem = None;
+        if let (true, Some(task_info)) = (self.components.mem, &task_info) {
+            // Page-ins are the faults that read from disk
+            let faults = u64::try_from(task_info.pti_faults).unwrap_or_default();
+            let pageins = u64::try_from(task_info.pti_pageins).unwrap_or_default();
+            mem = Some(MemStats {
+                minflt: faults.saturating_sub(pageins),
+                majflt: pageins,
+                cminflt: 0,
+                cmajflt: 0,
+                vsz: task_info.pti_virtual_size / 1024,
+                rss: task_info.pti_resident_size / 1024,
+                tot_mem: mem_total()? / 1024,
+                rss_anon: None,
+                rss_file: None,
+                rss_shmem: None,
+                time: now,
+            });
+        }
+        let mut io = None;
+        if self.components.io {
+            let rusage = pid_rusage(pid)?;
+            io = Some(IoStats {
+                read_bytes: rusage.ri_diskio_bytesread,
+                write_bytes: rusage.ri_diskio_byteswritten,
+                cancelled_write_bytes: 0,
+                blkio_swapin_delays: 0,
+                syscr: 0,
+                syscw: 0,
+                time: now,
+            });
+        }
+
+        let stats = Stats {
+            id,
+            components: ComponentStats {
+                cpu,
+                mem,
+                stack: None,
+                io,
+                ctx_switch: None,
+                signal: None,
+                oom: None,
+                addresses: None,
+                fd: None,
+                restricted: RestrictedComponents::default(),
+            },
+        };
+        Ok(stats)
+    }
+}
+
+/// The PIDs of every process
+fn list_all_pid() -> Vec<libc::pid_t> {
+    // SAFETY: a null buffer only asks for the number of processes
+    let n = unsafe { libc::proc_listallpids(std::ptr::null_mut(), 0) };
+    let Ok(n) = usize::try_from(n) else {
+        return vec![];
+    };
+    // Room for the processes started since counting
+    let mut pid: Vec<libc::pid_t> = vec![0; n + 64];
+    let size = c_int::try_from(mem::size_of_val(pid.as_slice())).expect("size");
+    // SAFETY: the buffer is `size` bytes long
+    let n = unsafe { libc::proc_listallpids(pid.as_mut_ptr().cast(), size) };
+    let Ok(n) = usize::try_from(n) else {
+        return vec![];
+    };
+    pid.truncate(n);
+    pid
+}
+
+/// The name of the process, which unlike `pbi_comm` is not cut at 16 bytes
+fn proc_name(pid: usize) -> Option<String> {
+    let pid = pid_t(pid).ok()?;
+    let mut name = [0_u8; 2 * libc::MAXCOMLEN + 1];
+    // SAFETY: the buffer is `name.len()` bytes long
+    let n = unsafe { libc::proc_name(pid, name.as_mut_ptr().cast(), name.len() as u32) };
+    let n = usize::try_from(n).ok().filter(|&n| n > 0)?;
+    Some(String::from_utf8_lossy(&name[..n]).into_owned())
+}
+
+/// When the process started
+fn bsd_start_time(bsd_info: &libc::proc_bsdinfo) -> SystemTime {
+    SystemTime::UNIX_EPOCH
+        + Duration::from_secs(bsd_info.pbi_start_tvsec)
+        + Duration::from_micros(bsd_info.pbi_start_tvusec)
+}
+
+/// Read the `flavor` of `proc_pidinfo` of `pid`, where `T` is the struct of the flavor
+fn pid_info<T: Copy>(pid: usize, flavor: c_int) -> Result<T, ReadStatsError> {
+    let pid = pid_t(pid)?;
+    let size = c_int::try_from(mem::size_of::<T>()).expect("size");
+    let mut info = MaybeUninit::<T>::zeroed();
+    // SAFETY: the buffer is `size` bytes long
+    let n = unsafe { libc::proc_pidinfo(pid, flavor, 0, info.as_mut_ptr().cast(), size) };
+    if n <= 0 {
+        let e = io::Error::last_os_error();
+        trace!(target: "pidstat::read::proc_pidinfo", pid, flavor, error = %e);
+        return Err(ReadStatsError::io(e));
+    }
+    if n < size {
+        return Err(ReadStatsError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+    // SAFETY: the buffer was zeroed and then filled, and `T` is a plain C struct
+    Ok(unsafe { info.assume_init() })
+}
+
+/// Read the resource usage of `pid`, including its disk I/O
+fn pid_rusage(pid: usize) -> Result<libc::rusage_info_v2, ReadStatsError> {
+    let pid = pid_t(pid)?;
+    let mut rusage = MaybeUninit::<libc::rusage_info_v2>::zeroed();
+    // SAFETY: `RUSAGE_INFO_V2` writes a `rusage_info_v2`
+    let r = unsafe {
+        libc::proc_pid_rusage(
+            pid,
+            libc::RUSAGE_INFO_V2,
+            rusage.as_mut_ptr().cast::<libc::rusage_info_t>(),
+        )
+    };
+    if r != 0 {
+        let e = io::Error::last_os_error();
+        trace!(target: "pidstat::read::proc_pid_rusage", pid, error = %e);
+        return Err(ReadStatsError::io(e));
+    }
+    // SAFETY: the buffer was zeroed and then filled
+    Ok(unsafe { rusage.assume_init() })
+}
+
+/// The physical memory in bytes
+fn mem_total() -> Result<u64, ReadStatsError> {
+    let mut name = [libc::CTL_HW, libc::HW_MEMSIZE];
+    let mut mem_total = 0_u64;
+    let mut len = mem::size_of::<u64>();
+    // SAFETY: `HW_MEMSIZE` writes a `u64` and `len` is its size
+    let r = unsafe {
+        libc::sysctl(
+            name.as_mut_ptr(),
+            name.len() as libc::c_uint,
+            (&mut mem_total as *mut u64).cast(),
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if r != 0 {
+        return Err(ReadStatsError::Io(io::Error::last_os_error()));
+    }
+    Ok(mem_total)
+}
+
+/// Convert the Mach absolute time units of task times, which are not nanoseconds on Apple
+/// silicon, to nanoseconds
+#[allow(deprecated)]
+fn mach_time_to_nanos(time: u64) -> u64 {
+    static TIMEBASE: OnceLock<(u32, u32)> = OnceLock::new();
+    let (numer, denom) = *TIMEBASE.get_or_init(|| {
+        let mut info = libc::mach_timebase_info { numer: 0, denom: 0 };
+        // SAFETY: `info` is a valid `mach_timebase_info`
+        match unsafe { libc::mach_timebase_info(&mut info) } {
+            0 if info.denom != 0 => (info.numer, info.denom),
+            _ => (1, 1),
+        }
+    });
+    (u128::from(time) * u128::from(numer) / u128::from(denom)) as u64
+}
+
+fn pid_t(pid: usize) -> Result<libc::pid_t, ReadStatsError> {
+    libc::pid_t::try_from(pid)
+        .map_err(|e| ReadStatsError::NoSuchProcess(io::Error::new(io::ErrorKind::InvalidInput, e)))
+}
+
+/// The string of a NUL-terminated C array
+fn c_string(chars: &[libc::c_char]) -> String {
+    let bytes = chars.iter().map(|&c| c as u8).collect::<Vec<_>>();
+    match CStr::from_bytes_until_nul(&bytes) {
+        Ok(s) => s.to_string_lossy().into_owned(),
+        Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
     }
 }
@@ -1,8 +1,13 @@
 use std::{
     collections::BTreeMap,
+    ffi::{CStr, CString},
+    mem::MaybeUninit,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::process::{ComponentStats, ProcessId};
@@ -11,8 +16,10 @@ use crate::process::{ComponentStats, ProcessId};
 pub mod linux;
 #[cfg(target_os = "macos")]
 pub mod macos;
+#[cfg(target_os = "linux")]
+pub mod taskstats;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ProcId {
     /// Or TGID if it's in the context of threads instead of processes
     pub pid: usize,
@@ -29,6 +36,23 @@ impl ProcId {
     }
 }
 
+/// What the running kernel offers that some columns depend on
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    /// Whether `/proc` is a mounted procfs, without which nothing can be read
+    pub procfs: bool,
+    /// Whether delay accounting is enabled, without which `%blkio` and `iodelay` are zero; `None`
+    /// if the kernel does not tell
+    pub delay_accounting: Option<bool>,
+    /// Whether `/proc/<pid>/schedstat` exists, without which `%wait` cannot be read
+    pub schedstat: bool,
+    pub clock_ticks_per_second: u64,
+    /// In bytes
+    pub page_size: usize,
+    /// `None` if unknown
+    pub online_cpus: Option<usize>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ReadStatsOptions {
     pub id: ProcId,
@@ -38,12 +62,64 @@ pub struct ReadStatsOptions {
 #[derive(Debug, Clone, Copy)]
 pub struct ComponentOptions {
     pub cpu: bool,
+    /// Whether to read the system-wide CPU time along with `cpu`
+    pub system_cpu: bool,
+    /// Whether to read the CPU time of schedstat along with `cpu`
+    pub sched_cpu: bool,
+    /// Whether to read the run queue wait of `cpu` from schedstat where taskstats is unavailable,
+    /// or leave it at zero
+    pub wait: bool,
     pub mem: bool,
     pub stack: bool,
+    /// Whether to size the stack by the stack mappings of `maps` instead of the main stack of
+    /// `smaps`
+    pub stack_from_maps: bool,
     pub io: bool,
     pub ctx_switch: bool,
+    pub signal: bool,
+    pub oom: bool,
+    pub addresses: bool,
+    /// Whether to read when the task started
+    pub start: bool,
+    /// Whether to count the open file descriptors and sockets
+    pub fd: bool,
+    /// Whether to read the delays over netlink instead of from `/proc` where available
+    pub taskstats: bool,
+    /// Whether to skip `stack`, `io` and `fd` for the tasks of other users instead of failing to
+    /// read them without the privileges of root
+    pub skip_unowned: bool,
+    /// The number of fields `/proc/<pid>/stat` must have, to fail on an unexpected layout instead
+    /// of misparsing it
+    pub stat_fields: Option<usize>,
 }
+impl ComponentOptions {
+    /// Without the components that need extra files read only for absolute values
+    pub fn without_absolute(&self) -> Self {
+        Self {
+            stack: false,
+            oom: false,
+            fd: false,
+            ..*self
+        }
+    }
 
+    /// Without the reads of files that other users may be denied, leaving `stat`, `status` and
+    /// world-readable files like `oom_score`
+    pub fn safe(&self) -> Self {
+        Self {
+            sched_cpu: false,
+            wait: false,
+            stack: false,
+            stack_from_maps: false,
+            io: false,
+            fd: false,
+            taskstats: false,
+            ..*self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stats {
     pub id: ProcessId,
     pub components: ComponentStats,
@@ -53,6 +129,57 @@ pub struct Stats {
 pub enum ReadStatsError {
     #[error("No such process: {0}")]
     NoSuchProcess(#[source] std::io::Error),
+    /// Any other failure to read, like a denied permission
+    #[error("Failed to read: {0}")]
+    Io(#[source] std::io::Error),
+    #[error("Malformed data: {0}")]
+    Parse(#[from] ParseError),
+    /// What the platform offers no way to read, like threads on macOS
+    #[error("{0} cannot be read on this platform")]
+    Unsupported(&'static str),
+}
+
+impl ReadStatsError {
+    /// [`Self::NoSuchProcess`] if the task is gone, like when its `/proc` directory vanished
+    /// mid-read, or [`Self::Io`] otherwise
+    pub fn io(error: std::io::Error) -> Self {
+        let gone = error.kind() == std::io::ErrorKind::NotFound
+            || rustix::io::Errno::from_io_error(&error) == Some(rustix::io::Errno::SRCH);
+        match gone {
+            true => Self::NoSuchProcess(error),
+            false => Self::Io(error),
+        }
+    }
+}
+
+/// Malformed `/proc` file contents
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("Missing `{0}`")]
+    Missing(&'static str),
+    #[error("`{file}` has {found} fields instead of the {expected} of the running kernel")]
+    FieldCount {
+        file: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    #[error("Invalid `{field}`: {source}")]
+    Invalid {
+        field: &'static str,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+impl ParseError {
+    pub fn invalid(
+        field: &'static str,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::Invalid {
+            field,
+            source: source.into(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -60,12 +187,25 @@ pub struct ReadTidOptions {
     pub tgid: usize,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct TaskOptions {
+    /// Whether to read the threads of the thread group
+    pub enabled: bool,
+    /// Read at most this many threads, the lowest TIDs first
+    pub max: Option<usize>,
+}
+
 pub async fn read_task_stats(
     pid: usize,
     components: ComponentOptions,
+    max: Option<usize>,
 ) -> Result<BTreeMap<usize, Stats>, ReadStatsError> {
     let mut task_stats = BTreeMap::new();
-    let tid = ReadTidOptions { tgid: pid }.read_tid().await?;
+    let mut tid = ReadTidOptions { tgid: pid }.read_tid().await?;
+    tid.sort_unstable();
+    if let Some(max) = max {
+        tid.truncate(max);
+    }
     for tid in tid {
         let options = ReadStatsOptions {
             id: ProcId {
@@ -74,21 +214,71 @@ pub async fn read_task_stats(
             },
             components,
         };
-        let stats = options.read_stats().await?;
+        let stats = match options.read_stats().await {
+            Ok(stats) => stats,
+            // The thread might have exited since the thread group was listed
+            Err(ReadStatsError::NoSuchProcess(_)) => continue,
+            Err(e) => return Err(e),
+        };
         task_stats.insert(tid, stats);
     }
     Ok(task_stats)
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskGroupStats {
     pub pid: usize,
     pub process: Stats,
     pub task: BTreeMap<usize, Stats>,
 }
+impl TaskGroupStats {
+    /// Fill in the components skipped by [`ComponentOptions::without_absolute`] from `prev`
+    pub fn reuse_absolute(&mut self, prev: &TaskGroupStats) {
+        let reuse = |stats: &mut Stats, prev: &Stats| {
+            let components = &mut stats.components;
+            if components.stack.is_none() {
+                components.stack = prev.components.stack.clone();
+            }
+            if components.oom.is_none() {
+                components.oom = prev.components.oom.clone();
+            }
+            if components.fd.is_none() {
+                components.fd = prev.components.fd.clone();
+            }
+            // Components skipped on this sample are not flagged, even if they were skipped
+            // before for being restricted
+            components.restricted.stack |= prev.components.restricted.stack;
+            components.restricted.fd |= prev.components.restricted.fd;
+        };
+        reuse(&mut self.process, &prev.process);
+        for (tid, stats) in &mut self.task {
+            if let Some(prev) = prev.task.get(tid) {
+                reuse(stats, prev);
+            }
+        }
+    }
+
+    /// A sample as if taken when every task started, to report totals against
+    pub fn since_start(&self) -> Self {
+        let since_start = |stats: &Stats| Stats {
+            id: stats.id.clone(),
+            components: stats.components.since_start(),
+        };
+        Self {
+            pid: self.pid,
+            process: since_start(&self.process),
+            task: self
+                .task
+                .iter()
+                .map(|(tid, stats)| (*tid, since_start(stats)))
+                .collect(),
+        }
+    }
+}
 pub async fn read_task_group_stats(
     pid: usize,
     components: ComponentOptions,
-    task: bool,
+    task: TaskOptions,
 ) -> Result<TaskGroupStats, ReadStatsError> {
     let process_options = ReadStatsOptions {
         id: ProcId { pid, tid: None },
@@ -96,8 +286,8 @@ pub async fn read_task_group_stats(
     };
     let process_stats = process_options.read_stats().await?;
     let mut task_stats = BTreeMap::new();
-    if task {
-        task_stats = read_task_stats(pid, components).await?;
+    if task.enabled {
+        task_stats = read_task_stats(pid, components, task.max).await?;
     }
     Ok(TaskGroupStats {
         pid,
@@ -106,6 +296,139 @@ pub async fn read_task_group_stats(
     })
 }
 
+/// The release of the running kernel with the layout of `/proc/<pid>/stat` it writes
+#[derive(Debug, Clone)]
+pub struct StatLayout {
+    /// Like `6.1.0-13-amd64`
+    pub release: String,
+    /// `None` if the release is not understood
+    pub fields: Option<usize>,
+}
+impl StatLayout {
+    /// Ref: <https://man7.org/linux/man-pages/man5/proc_pid_stat.5.html>
+    pub fn new(release: String) -> Self {
+        let mut numbers = release
+            .split(|c: char| !c.is_ascii_digit())
+            .map(|n| n.parse::<u32>().ok());
+        let version = match (numbers.next(), numbers.next(), numbers.next()) {
+            (Some(Some(major)), Some(Some(minor)), patch) => {
+                Some((major, minor, patch.flatten().unwrap_or(0)))
+            }
+            _ => None,
+        };
+        let fields = version.map(|version| match version {
+            // From `arg_start` to `exit_code`
+            v if (3, 5, 0) <= v => 52,
+            // From `start_data` to `start_brk`
+            v if (3, 3, 0) <= v => 47,
+            // `guest_time` and `cguest_time`
+            v if (2, 6, 24) <= v => 44,
+            // `delayacct_blkio_ticks`
+            v if (2, 6, 18) <= v => 42,
+            _ => 41,
+        });
+        Self { release, fields }
+    }
+
+    /// Read the release from `/proc/sys/kernel/osrelease`
+    pub async fn read() -> Result<Self, std::io::Error> {
+        let release = tokio::fs::read_to_string("/proc/sys/kernel/osrelease").await?;
+        Ok(Self::new(release.trim_end().to_string()))
+    }
+}
+
+/// Look up the UID of the user `name` through the name service switch, which also knows the
+/// users of LDAP or sssd besides those of `/etc/passwd`
+pub async fn read_user_uid(name: &str) -> Result<Option<usize>, std::io::Error> {
+    let name =
+        CString::new(name).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    // The lookup may block on the network
+    tokio::task::spawn_blocking(move || user_uid(&name))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+fn user_uid(name: &CStr) -> Result<Option<usize>, std::io::Error> {
+    let mut buf = vec![0_u8; 1024];
+    loop {
+        let mut passwd = MaybeUninit::<libc::passwd>::zeroed();
+        let mut result = std::ptr::null_mut();
+        // SAFETY: `name` is NUL-terminated and `buf` is `buf.len()` bytes long
+        let e = unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                passwd.as_mut_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match e {
+            0 if result.is_null() => return Ok(None),
+            // SAFETY: `result` points to `passwd`, which was filled
+            0 => return Ok(Some(unsafe { passwd.assume_init() }.pw_uid as usize)),
+            libc::ERANGE => buf.resize(buf.len() * 2, 0),
+            e => return Err(std::io::Error::from_raw_os_error(e)),
+        }
+    }
+}
+
+/// Read the PIDs in the cgroup of the systemd unit `unit`, such as `nginx.service`
+///
+/// Tries the unified hierarchy first and then the `systemd` hierarchy of cgroup v1.
+/// Fails with [`std::io::ErrorKind::NotFound`] if the unit has no cgroup.
+pub async fn read_unit_pid(unit: &str) -> Result<Vec<usize>, std::io::Error> {
+    const HIERARCHIES: [&str; 3] = [
+        "/sys/fs/cgroup",
+        "/sys/fs/cgroup/unified",
+        "/sys/fs/cgroup/systemd",
+    ];
+    for hierarchy in HIERARCHIES {
+        let path = Path::new(hierarchy)
+            .join("system.slice")
+            .join(unit)
+            .join("cgroup.procs");
+        let text = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let mut pid = text
+            .lines()
+            .filter_map(|line| line.parse().ok())
+            .collect::<Vec<usize>>();
+        pid.sort_unstable();
+        pid.dedup();
+        return Ok(pid);
+    }
+    Err(std::io::ErrorKind::NotFound.into())
+}
+
+/// How `process-name` selects processes by their command
+#[derive(Debug, Clone)]
+pub enum NameMatcher {
+    /// The command is exactly this
+    Exact(String),
+    /// The command contains this
+    Substring(String),
+    /// The command matches this somewhere
+    Regex(Regex),
+}
+impl NameMatcher {
+    pub fn is_match(&self, command: &str) -> bool {
+        match self {
+            NameMatcher::Exact(name) => command == name,
+            NameMatcher::Substring(name) => command.contains(name.as_str()),
+            NameMatcher::Regex(regex) => regex.is_match(command),
+        }
+    }
+}
+
 pub struct ReadPidOptions<'a> {
-    pub process_name: &'a str,
+    /// Keep every command if `None`
+    pub process_name: Option<&'a NameMatcher>,
+    /// Only keep processes of this real UID
+    pub uid: Option<usize>,
+    /// Only keep processes that started at least this long ago
+    pub min_lifetime: Option<Duration>,
 }
@@ -0,0 +1,222 @@
+use super::ProcId;
+
+/// Ref: <https://docs.kernel.org/accounting/taskstats.html>
+#[derive(Debug, Clone, Copy)]
+pub struct Taskstats {
+    /// Time waiting on a run queue, in nanoseconds
+    pub cpu_delay_total: u64,
+    /// Time waiting for block I/O to complete, in nanoseconds
+    pub blkio_delay_total: u64,
+    /// Time waiting for pages to be swapped in, in nanoseconds
+    pub swapin_delay_total: u64,
+    /// Voluntary context switches
+    pub nvcsw: u64,
+    /// Involuntary context switches
+    pub nivcsw: u64,
+}
+
+/// Read the delay accounting of the task over generic netlink, or `None` to fall back to `/proc`.
+///
+/// Always `None` without the `taskstats` feature.
+#[cfg(feature = "taskstats")]
+pub fn read_taskstats(id: ProcId) -> Option<Taskstats> {
+    use std::sync::{Mutex, OnceLock};
+
+    use tracing::trace;
+
+    // Resolving the family takes a round trip, so the socket is kept for the whole run
+    static SOCKET: OnceLock<Option<Mutex<netlink::TaskstatsSocket>>> = OnceLock::new();
+    let socket = SOCKET.get_or_init(|| {
+        netlink::TaskstatsSocket::open()
+            .inspect_err(|e| trace!(target: "pidstat::read::taskstats", error = %e))
+            .ok()
+            .map(Mutex::new)
+    });
+    let mut socket = socket.as_ref()?.lock().unwrap();
+    let taskstats = match id.tid {
+        Some(tid) => socket.read(netlink::Target::Pid(tid)),
+        None => socket.read(netlink::Target::Tgid(id.pid)),
+    };
+    let taskstats = taskstats
+        .inspect_err(|e| trace!(target: "pidstat::read::taskstats", ?id, error = %e))
+        .ok()?;
+    trace!(target: "pidstat::read::taskstats", ?id, ?taskstats);
+    Some(taskstats)
+}
+
+#[cfg(not(feature = "taskstats"))]
+pub fn read_taskstats(_id: ProcId) -> Option<Taskstats> {
+    None
+}
+
+#[cfg(feature = "taskstats")]
+mod netlink {
+    use std::{io, os::fd::OwnedFd};
+
+    use rustix::net::{AddressFamily, RecvFlags, SendFlags, SocketType};
+
+    use super::Taskstats;
+
+    const NLMSG_ERROR: u16 = 2;
+    const NLM_F_REQUEST: u16 = 1;
+    const GENL_ID_CTRL: u16 = 0x10;
+    const CTRL_CMD_GETFAMILY: u8 = 3;
+    const CTRL_ATTR_FAMILY_ID: u16 = 1;
+    const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+    const TASKSTATS_CMD_GET: u8 = 1;
+    const TASKSTATS_CMD_ATTR_PID: u16 = 1;
+    const TASKSTATS_CMD_ATTR_TGID: u16 = 2;
+    const TASKSTATS_TYPE_STATS: u16 = 3;
+    const TASKSTATS_TYPE_AGGR_PID: u16 = 4;
+    const TASKSTATS_TYPE_AGGR_TGID: u16 = 5;
+    const NLMSG_HDRLEN: usize = 16;
+    const GENL_HDRLEN: usize = 4;
+    const NLA_HDRLEN: usize = 4;
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum Target {
+        Pid(usize),
+        Tgid(usize),
+    }
+
+    pub struct TaskstatsSocket {
+        fd: OwnedFd,
+        family: u16,
+        seq: u32,
+    }
+    impl TaskstatsSocket {
+        pub fn open() -> io::Result<Self> {
+            let fd = rustix::net::socket(
+                AddressFamily::NETLINK,
+                SocketType::DGRAM,
+                Some(rustix::net::netlink::GENERIC),
+            )?;
+            let mut socket = Self {
+                fd,
+                family: GENL_ID_CTRL,
+                seq: 0,
+            };
+            let reply = socket.request(
+                GENL_ID_CTRL,
+                CTRL_CMD_GETFAMILY,
+                CTRL_ATTR_FAMILY_NAME,
+                b"TASKSTATS\0",
+            )?;
+            let family = attributes(&reply)
+                .find(|(kind, _)| *kind == CTRL_ATTR_FAMILY_ID)
+                .and_then(|(_, value)| Some(u16::from_ne_bytes(value.get(..2)?.try_into().ok()?)))
+                .ok_or_else(|| invalid("no family ID"))?;
+            socket.family = family;
+            Ok(socket)
+        }
+
+        pub fn read(&mut self, target: Target) -> io::Result<Taskstats> {
+            let (attribute, id) = match target {
+                Target::Pid(pid) => (TASKSTATS_CMD_ATTR_PID, pid),
+                Target::Tgid(tgid) => (TASKSTATS_CMD_ATTR_TGID, tgid),
+            };
+            let id = u32::try_from(id).map_err(|_| invalid("PID out of range"))?;
+            let reply =
+                self.request(self.family, TASKSTATS_CMD_GET, attribute, &id.to_ne_bytes())?;
+            let aggregate = attributes(&reply)
+                .find(|(kind, _)| {
+                    matches!(*kind, TASKSTATS_TYPE_AGGR_PID | TASKSTATS_TYPE_AGGR_TGID)
+                })
+                .ok_or_else(|| invalid("no aggregate"))?
+                .1;
+            let stats = attributes(aggregate)
+                .find(|(kind, _)| *kind == TASKSTATS_TYPE_STATS)
+                .ok_or_else(|| invalid("no stats"))?
+                .1;
+            parse_taskstats(stats).ok_or_else(|| invalid("truncated stats"))
+        }
+
+        /// Send a generic netlink request of a single attribute and return the attributes of the
+        /// reply
+        fn request(
+            &mut self,
+            family: u16,
+            command: u8,
+            attribute: u16,
+            value: &[u8],
+        ) -> io::Result<Vec<u8>> {
+            self.seq = self.seq.wrapping_add(1);
+            let attribute_len = NLA_HDRLEN + value.len();
+            let len = NLMSG_HDRLEN + GENL_HDRLEN + align(attribute_len);
+            let mut message = Vec::with_capacity(len);
+            message.extend_from_slice(&(len as u32).to_ne_bytes());
+            message.extend_from_slice(&family.to_ne_bytes());
+            message.extend_from_slice(&NLM_F_REQUEST.to_ne_bytes());
+            message.extend_from_slice(&self.seq.to_ne_bytes());
+            message.extend_from_slice(&0_u32.to_ne_bytes());
+            message.extend_from_slice(&[command, 1, 0, 0]);
+            message.extend_from_slice(&(attribute_len as u16).to_ne_bytes());
+            message.extend_from_slice(&attribute.to_ne_bytes());
+            message.extend_from_slice(value);
+            message.resize(len, 0);
+            rustix::net::send(&self.fd, &message, SendFlags::empty())?;
+
+            let mut buf = vec![0; 8192];
+            loop {
+                let n = rustix::net::recv(&self.fd, &mut buf, RecvFlags::empty())?;
+                let reply = buf.get(..n).ok_or_else(|| invalid("truncated reply"))?;
+                let header = reply
+                    .get(..NLMSG_HDRLEN)
+                    .ok_or_else(|| invalid("truncated reply"))?;
+                let kind = u16::from_ne_bytes(header[4..6].try_into().unwrap());
+                let seq = u32::from_ne_bytes(header[8..12].try_into().unwrap());
+                // A reply to an earlier request that failed half way
+                if seq != self.seq {
+                    continue;
+                }
+                if kind == NLMSG_ERROR {
+                    let errno = reply
+                        .get(NLMSG_HDRLEN..NLMSG_HDRLEN + 4)
+                        .map(|b| i32::from_ne_bytes(b.try_into().unwrap()))
+                        .ok_or_else(|| invalid("truncated error"))?;
+                    return Err(io::Error::from_raw_os_error(-errno));
+                }
+                let len = u32::from_ne_bytes(header[0..4].try_into().unwrap()) as usize;
+                let payload = reply
+                    .get(NLMSG_HDRLEN + GENL_HDRLEN..len.min(n))
+                    .ok_or_else(|| invalid("truncated reply"))?;
+                return Ok(payload.to_vec());
+            }
+        }
+    }
+
+    /// The `(type, value)` of each netlink attribute in `buf`
+    fn attributes(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+        std::iter::from_fn(move || {
+            let len = u16::from_ne_bytes(buf.get(0..2)?.try_into().ok()?) as usize;
+            // Without the nested and byte order flags
+            let kind = u16::from_ne_bytes(buf.get(2..4)?.try_into().ok()?) & 0x3fff;
+            let value = buf.get(NLA_HDRLEN..len)?;
+            buf = buf.get(align(len)..).unwrap_or_default();
+            Some((kind, value))
+        })
+    }
+
+    fn parse_taskstats(buf: &[u8]) -> Option<Taskstats> {
+        let u64_at = |offset: usize| {
+            Some(u64::from_ne_bytes(
+                buf.get(offset..offset + 8)?.try_into().ok()?,
+            ))
+        };
+        Some(Taskstats {
+            cpu_delay_total: u64_at(24)?,
+            blkio_delay_total: u64_at(40)?,
+            swapin_delay_total: u64_at(56)?,
+            nvcsw: u64_at(272)?,
+            nivcsw: u64_at(280)?,
+        })
+    }
+
+    fn align(len: usize) -> usize {
+        (len + 3) & !3
+    }
+
+    fn invalid(message: &'static str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+}
@@ -0,0 +1,300 @@
+use std::time::{Duration, SystemTime};
+
+use common::ChangeMode;
+use serde::{Deserialize, Serialize};
+
+use crate::read::{ReadStatsError, Stats, TaskGroupStats};
+
+/// A sample of a task for structured output.
+///
+/// Changes are per second, or totals for [`ChangeMode::Total`], like in the tables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleRecord {
+    /// Seconds since the Unix epoch
+    pub timestamp: f64,
+    /// The interval the record was sampled on, counting from 0; a PID missing from an interval
+    /// skips its index
+    #[serde(default)]
+    pub sample_index: u64,
+    /// The interval configured for the sample in seconds, against which `timestamp` drifts when
+    /// sampling was held up
+    #[serde(default)]
+    pub interval_secs: f64,
+    pub uid: usize,
+    pub pid: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tid: Option<usize>,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<CpuRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem: Option<MemRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<StackRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub io: Option<IoRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ctx_switch: Option<CtxSwitchRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signal: Option<SignalRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oom: Option<OomRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fd: Option<FdRecord>,
+}
+
+/// In percent of a CPU, or in seconds for [`ChangeMode::Total`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuRecord {
+    pub usr: f64,
+    pub system: f64,
+    pub guest: f64,
+    pub wait: f64,
+    pub blkio: f64,
+    pub cpu: f64,
+    pub processor: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemRecord {
+    pub minflt: f64,
+    pub majflt: f64,
+    /// In kB
+    pub vsz: u64,
+    /// In kB
+    pub rss: u64,
+    /// In percent of the total memory
+    pub mem: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackRecord {
+    /// In kB
+    pub stk_size: u64,
+    /// In kB
+    pub stk_ref: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IoRecord {
+    pub kb_rd: f64,
+    pub kb_wr: f64,
+    pub kb_ccwr: f64,
+    /// In clock ticks
+    pub iodelay: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CtxSwitchRecord {
+    pub cswch: f64,
+    pub nvcswch: f64,
+}
+
+/// Bit `n - 1` of each mask stands for signal `n`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalRecord {
+    pub blocked: u64,
+    pub ignored: u64,
+    pub caught: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OomRecord {
+    pub score: u64,
+    pub score_adj: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FdRecord {
+    pub fds: u64,
+    pub sockets: u64,
+}
+
+impl SampleRecord {
+    pub fn new(prev: &Stats, curr: &Stats, change: ChangeMode, timestamp: SystemTime) -> Self {
+        let timestamp = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let prev_components = &prev.components;
+        let curr_components = &curr.components;
+        let rate = |prev: u64, curr: u64, interval: Duration| {
+            change
+                .change(prev.into(), curr.into(), interval)
+                .unwrap()
+                .get()
+        };
+
+        let mut cpu = None;
+        if let (Some(prev), Some(curr)) = (&prev_components.cpu, &curr_components.cpu) {
+            let interval = curr.time - prev.time;
+            // In percent, or in seconds for `ChangeMode::Total`
+            let scale = match change {
+                ChangeMode::PerSecond => 100.,
+                ChangeMode::Total => 1.,
+            } / curr.clock_ticks_per_second as f64;
+            let time = |prev: u64, curr: u64| rate(prev, curr, interval) * scale;
+            cpu = Some(CpuRecord {
+                usr: time(prev.user_time, curr.user_time),
+                system: time(prev.system_time, curr.system_time),
+                guest: time(prev.guest_time, curr.guest_time),
+                wait: time(prev.wait_time, curr.wait_time),
+                blkio: time(prev.blkio_delay, curr.blkio_delay),
+                cpu: time(
+                    prev.user_time + prev.system_time + prev.wait_time,
+                    curr.user_time + curr.system_time + curr.wait_time,
+                ),
+                processor: curr.processor,
+            });
+        }
+        let mut mem = None;
+        if let (Some(prev), Some(curr)) = (&prev_components.mem, &curr_components.mem) {
+            let interval = curr.time - prev.time;
+            mem = Some(MemRecord {
+                minflt: rate(prev.minflt, curr.minflt, interval),
+                majflt: rate(prev.majflt, curr.majflt, interval),
+                vsz: curr.vsz,
+                rss: curr.rss,
+                mem: curr.rss as f64 / curr.tot_mem as f64 * 100.,
+            });
+        }
+        let stack = curr_components.stack.as_ref().map(|curr| StackRecord {
+            stk_size: curr.stk_size,
+            stk_ref: curr.stk_ref,
+        });
+        let mut io = None;
+        if let (Some(prev), Some(curr)) = (&prev_components.io, &curr_components.io) {
+            let interval = curr.time - prev.time;
+            let kb = |prev: u64, curr: u64| rate(prev, curr, interval) / 1024.;
+            io = Some(IoRecord {
+                kb_rd: kb(prev.read_bytes, curr.read_bytes),
+                kb_wr: kb(prev.write_bytes, curr.write_bytes),
+                kb_ccwr: kb(prev.cancelled_write_bytes, curr.cancelled_write_bytes),
+                iodelay: curr.blkio_swapin_delays - prev.blkio_swapin_delays,
+            });
+        }
+        let mut ctx_switch = None;
+        if let (Some(prev), Some(curr)) = (&prev_components.ctx_switch, &curr_components.ctx_switch)
+        {
+            let interval = curr.time - prev.time;
+            ctx_switch = Some(CtxSwitchRecord {
+                cswch: rate(prev.nvcsw, curr.nvcsw, interval),
+                nvcswch: rate(prev.nivcsw, curr.nivcsw, interval),
+            });
+        }
+        let signal = curr_components.signal.as_ref().map(|curr| SignalRecord {
+            blocked: curr.blocked,
+            ignored: curr.ignored,
+            caught: curr.caught,
+        });
+        let oom = curr_components.oom.as_ref().map(|curr| OomRecord {
+            score: curr.score,
+            score_adj: curr.score_adj,
+        });
+        let fd = curr_components.fd.as_ref().map(|curr| FdRecord {
+            fds: curr.fds,
+            sockets: curr.sockets,
+        });
+
+        Self {
+            timestamp,
+            sample_index: 0,
+            interval_secs: 0.,
+            uid: curr.id.uid,
+            pid: curr.id.proc_id.pid,
+            tid: curr.id.proc_id.tid,
+            command: curr.id.command.clone(),
+            cpu,
+            mem,
+            stack,
+            io,
+            ctx_switch,
+            signal,
+            oom,
+            fd,
+        }
+    }
+
+    /// The record of the interval `index` with the configured `interval`
+    pub fn with_sample(self, index: u64, interval: Duration) -> Self {
+        Self {
+            sample_index: index,
+            interval_secs: interval.as_secs_f64(),
+            ..self
+        }
+    }
+}
+
+/// A failed read of a process for structured output, so that a process that exited can be told
+/// apart from one that was never sampled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventRecord {
+    Error {
+        /// Seconds since the Unix epoch
+        timestamp: f64,
+        pid: usize,
+        kind: ErrorKind,
+    },
+}
+impl EventRecord {
+    pub fn error(pid: usize, error: &ReadStatsError, timestamp: SystemTime) -> Self {
+        let timestamp = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        Self::Error {
+            timestamp,
+            pid,
+            kind: ErrorKind::new(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    NoSuchProcess,
+    PermissionDenied,
+    /// Any other I/O error
+    Io,
+    /// Malformed `/proc` file contents
+    Parse,
+    /// Not readable on the platform
+    Unsupported,
+}
+impl ErrorKind {
+    pub fn new(error: &ReadStatsError) -> Self {
+        match error {
+            ReadStatsError::NoSuchProcess(_) => Self::NoSuchProcess,
+            ReadStatsError::Io(e) => match e.kind() {
+                std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+                _ => Self::Io,
+            },
+            ReadStatsError::Parse(_) => Self::Parse,
+            ReadStatsError::Unsupported(_) => Self::Unsupported,
+        }
+    }
+}
+
+/// The records of the process followed by the records of its tasks present in both samples
+pub fn task_group_records(
+    prev: &TaskGroupStats,
+    curr: &TaskGroupStats,
+    change: ChangeMode,
+    timestamp: SystemTime,
+) -> Vec<SampleRecord> {
+    let mut records = vec![SampleRecord::new(
+        &prev.process,
+        &curr.process,
+        change,
+        timestamp,
+    )];
+    for (tid, stats) in &curr.task {
+        let Some(prev_stats) = prev.task.get(tid) else {
+            continue;
+        };
+        records.push(SampleRecord::new(prev_stats, stats, change, timestamp));
+    }
+    records
+}
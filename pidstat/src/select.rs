@@ -0,0 +1,181 @@
+use std::{collections::BTreeSet, future::Future, pin::Pin};
+
+use tokio::sync::OnceCell;
+
+use crate::read::{read_unit_pid, ReadPidOptions};
+
+pub type PidFuture<'a> = Pin<Box<dyn Future<Output = Vec<usize>> + Send + 'a>>;
+
+/// Decides which processes to monitor; resolved again on every interval
+pub trait PidSelector: Send + Sync {
+    fn resolve(&self) -> PidFuture<'_>;
+}
+
+/// Exactly these PIDs, whether or not they exist
+#[derive(Debug, Clone)]
+pub struct Pids(pub Vec<usize>);
+impl PidSelector for Pids {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(async { self.0.clone() })
+    }
+}
+
+/// The processes in the cgroup of a systemd unit, or none if it has stopped
+#[derive(Debug, Clone)]
+pub struct Unit(pub String);
+impl PidSelector for Unit {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(async { read_unit_pid(&self.0).await.unwrap_or_default() })
+    }
+}
+
+/// The processes in `/proc` that pass every filter
+impl PidSelector for ReadPidOptions<'_> {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(self.read_pid())
+    }
+}
+
+/// The processes selected by every selector, in the order of the first
+pub struct AllOf<'a>(pub Vec<Box<dyn PidSelector + 'a>>);
+impl PidSelector for AllOf<'_> {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(async {
+            let mut selectors = self.0.iter();
+            let Some(first) = selectors.next() else {
+                return vec![];
+            };
+            let mut pid = first.resolve().await;
+            for selector in selectors {
+                let selected = selector
+                    .resolve()
+                    .await
+                    .into_iter()
+                    .collect::<BTreeSet<_>>();
+                pid.retain(|p| selected.contains(p));
+            }
+            pid
+        })
+    }
+}
+
+/// The processes selected by any selector, each once, in the order they are first selected
+pub struct AnyOf<'a>(pub Vec<Box<dyn PidSelector + 'a>>);
+impl PidSelector for AnyOf<'_> {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(async {
+            let mut seen = BTreeSet::new();
+            let mut pid = vec![];
+            for selector in &self.0 {
+                for p in selector.resolve().await {
+                    if seen.insert(p) {
+                        pid.push(p);
+                    }
+                }
+            }
+            pid
+        })
+    }
+}
+
+/// The processes selected by `selector` when first resolved, for the whole run
+pub struct Locked<'a> {
+    selector: Box<dyn PidSelector + 'a>,
+    pid: OnceCell<Vec<usize>>,
+}
+impl<'a> Locked<'a> {
+    pub fn new(selector: Box<dyn PidSelector + 'a>) -> Self {
+        Self {
+            selector,
+            pid: OnceCell::new(),
+        }
+    }
+}
+impl PidSelector for Locked<'_> {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(async {
+            self.pid
+                .get_or_init(|| self.selector.resolve())
+                .await
+                .clone()
+        })
+    }
+}
+
+/// The processes selected by `selector` other than `excluded`
+pub struct Except<'a> {
+    pub selector: Box<dyn PidSelector + 'a>,
+    pub excluded: Vec<usize>,
+}
+impl PidSelector for Except<'_> {
+    fn resolve(&self) -> PidFuture<'_> {
+        Box::pin(async {
+            let mut pid = self.selector.resolve().await;
+            pid.retain(|p| !self.excluded.contains(p));
+            pid
+        })
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{
+        io,
+        os::unix::fs::PermissionsExt,
+        path::Path,
+        process::{Child, Command},
+        time::Duration,
+    };
+
+    use crate::read::NameMatcher;
+
+    use super::*;
+
+    /// Run a script that sleeps until killed, whose processes take the name of its file
+    fn spawn_named(path: &Path) -> Child {
+        std::fs::write(path, "#!/bin/sh\nwhile :; do sleep 1; done\n").unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        loop {
+            match Command::new(path).spawn() {
+                // A thread of another test forked while the file was open for writing
+                Err(e) if e.kind() == io::ErrorKind::ExecutableFileBusy => continue,
+                child => return child.unwrap(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_picks_up_new_processes() {
+        let name = format!("pssel{}", std::process::id());
+        let matcher = NameMatcher::Exact(name.clone());
+        let options = || ReadPidOptions {
+            process_name: Some(&matcher),
+            uid: None,
+            min_lifetime: None,
+        };
+        let refreshed = options();
+        let locked = Locked::new(Box::new(options()));
+        assert!(refreshed.resolve().await.is_empty());
+        assert!(locked.resolve().await.is_empty());
+
+        let path = std::env::temp_dir().join(&name);
+        let mut child = spawn_named(&path);
+        let pid = usize::try_from(child.id()).unwrap();
+        // The name is only taken once the script is executed
+        let mut selected = vec![];
+        for _ in 0..50 {
+            selected = refreshed.resolve().await;
+            if !selected.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let locked_selected = locked.resolve().await;
+        child.kill().unwrap();
+        child.wait().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(selected, [pid]);
+        assert!(locked_selected.is_empty());
+    }
+}
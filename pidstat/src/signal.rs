@@ -0,0 +1,150 @@
+use core::fmt;
+use std::time::Instant;
+
+use common::value::{
+    int_stat_color, normal_color, start_column, write_columns, zero_int_stat_color,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::process::{
+    CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+    TidDisplayOption,
+};
+
+/// Bit `n - 1` of each mask stands for signal `n`.
+///
+/// Only covers the first 31 signals; real-time signals are not reported in `/proc/<pid>/stat`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalStats {
+    pub blocked: u64,
+    pub ignored: u64,
+    pub caught: u64,
+    #[serde(skip, default = "Instant::now")]
+    pub time: Instant,
+}
+
+const WIDTH: usize = 23;
+
+#[derive(Debug, Clone)]
+pub struct SignalStatsHeaderDisplay {
+    pub tid: TidDisplayOption,
+}
+impl fmt::Display for SignalStatsHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        for name in ["Blocked", "Ignored", "Caught"] {
+            start_column(f)?;
+            write!(f, " {name:<WIDTH$}")?;
+        }
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SignalStatsValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub curr_stats: &'a SignalStats,
+}
+impl<'a> fmt::Display for SignalStatsValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+
+        for mask in [
+            self.curr_stats.blocked,
+            self.curr_stats.ignored,
+            self.curr_stats.caught,
+        ] {
+            let start = match mask {
+                0 => zero_int_stat_color(),
+                _ => int_stat_color(),
+            };
+            let names = SignalMaskDisplay { mask }.to_string();
+            start_column(f)?;
+            write!(f, "{start} {names:<WIDTH$}{end}", end = normal_color())?;
+        }
+
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
+
+        Ok(())
+    }
+}
+
+/// Comma-separated signal names of a signal mask, or `-` if the mask is empty
+pub struct SignalMaskDisplay {
+    pub mask: u64,
+}
+impl fmt::Display for SignalMaskDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mask == 0 {
+            return write!(f, "-");
+        }
+        let mut first = true;
+        for signo in 1..=u64::BITS {
+            if self.mask & (1 << (signo - 1)) == 0 {
+                continue;
+            }
+            if !first {
+                write!(f, ",")?;
+            }
+            first = false;
+            match signal_name(signo) {
+                Some(name) => write!(f, "{name}")?,
+                None => write!(f, "{signo}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Signal names without the `SIG` prefix as numbered on x86 and ARM Linux
+pub fn signal_name(signo: u32) -> Option<&'static str> {
+    Some(match signo {
+        1 => "HUP",
+        2 => "INT",
+        3 => "QUIT",
+        4 => "ILL",
+        5 => "TRAP",
+        6 => "ABRT",
+        7 => "BUS",
+        8 => "FPE",
+        9 => "KILL",
+        10 => "USR1",
+        11 => "SEGV",
+        12 => "USR2",
+        13 => "PIPE",
+        14 => "ALRM",
+        15 => "TERM",
+        16 => "STKFLT",
+        17 => "CHLD",
+        18 => "CONT",
+        19 => "STOP",
+        20 => "TSTP",
+        21 => "TTIN",
+        22 => "TTOU",
+        23 => "URG",
+        24 => "XCPU",
+        25 => "XFSZ",
+        26 => "VTALRM",
+        27 => "PROF",
+        28 => "WINCH",
+        29 => "IO",
+        30 => "PWR",
+        31 => "SYS",
+        _ => return None,
+    })
+}
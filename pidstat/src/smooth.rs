@@ -0,0 +1,127 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use crate::read::{Stats, TaskGroupStats};
+
+/// Spreads the bursts of counters that the kernel only updates every so often, like those of
+/// delay accounting and of `/proc/<pid>/io` on some filesystems, so that they do not read as a
+/// spike followed by zeros.
+///
+/// This is an estimation: when a counter changes, its increase is assumed to have accumulated
+/// evenly since its previous change, and that rate is carried forward over the following
+/// intervals until the reported value catches up with the real one. The reported value never
+/// runs ahead of the real one, so the totals agree once the counter goes quiet.
+#[derive(Debug, Clone, Default)]
+pub struct CounterSmoother {
+    /// By PID and then by TID, or `None` for the process itself
+    tasks: BTreeMap<usize, BTreeMap<Option<usize>, TaskCounters>>,
+}
+impl CounterSmoother {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the counters of `stats` with their smoothed values
+    pub fn smooth(&mut self, stats: &mut TaskGroupStats) {
+        let tasks = self.tasks.entry(stats.pid).or_default();
+        // Threads that exited no longer need their counters
+        tasks.retain(|tid, _| tid.is_none_or(|tid| stats.task.contains_key(&tid)));
+        tasks.entry(None).or_default().smooth(&mut stats.process);
+        for (&tid, task) in &mut stats.task {
+            tasks.entry(Some(tid)).or_default().smooth(task);
+        }
+    }
+
+    pub fn forget(&mut self, pid: usize) {
+        self.tasks.remove(&pid);
+    }
+
+    /// Forget every process for which `f` returns `false`
+    pub fn retain(&mut self, mut f: impl FnMut(usize) -> bool) {
+        self.tasks.retain(|pid, _| f(*pid));
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct TaskCounters {
+    blkio_delay: Option<Counter>,
+    read_bytes: Option<Counter>,
+    write_bytes: Option<Counter>,
+    cancelled_write_bytes: Option<Counter>,
+    blkio_swapin_delays: Option<Counter>,
+}
+impl TaskCounters {
+    fn smooth(&mut self, stats: &mut Stats) {
+        fn smooth(counter: &mut Option<Counter>, value: &mut u64, time: Instant) {
+            *value = match counter {
+                Some(counter) => counter.update(*value, time),
+                None => {
+                    *counter = Some(Counter::new(*value, time));
+                    *value
+                }
+            };
+        }
+        if let Some(cpu) = &mut stats.components.cpu {
+            smooth(&mut self.blkio_delay, &mut cpu.blkio_delay, cpu.time);
+        }
+        if let Some(io) = &mut stats.components.io {
+            smooth(&mut self.read_bytes, &mut io.read_bytes, io.time);
+            smooth(&mut self.write_bytes, &mut io.write_bytes, io.time);
+            smooth(
+                &mut self.cancelled_write_bytes,
+                &mut io.cancelled_write_bytes,
+                io.time,
+            );
+            smooth(
+                &mut self.blkio_swapin_delays,
+                &mut io.blkio_swapin_delays,
+                io.time,
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Counter {
+    /// The real value
+    value: u64,
+    /// When the real value last changed
+    changed: Instant,
+    /// The time of the last sample
+    sampled: Instant,
+    /// The estimated increase per second
+    rate: f64,
+    /// The value reported for the last sample
+    reported: f64,
+}
+impl Counter {
+    fn new(value: u64, time: Instant) -> Self {
+        Self {
+            value,
+            changed: time,
+            sampled: time,
+            rate: 0.,
+            reported: value as f64,
+        }
+    }
+
+    /// Return the value to report for the real `value` sampled at `time`
+    fn update(&mut self, value: u64, time: Instant) -> u64 {
+        if value < self.value {
+            // The counter was reset, so nothing before it is comparable
+            *self = Self::new(value, time);
+            return value;
+        }
+        let elapsed = time.saturating_duration_since(self.sampled).as_secs_f64();
+        if value != self.value {
+            let span = time.saturating_duration_since(self.changed).as_secs_f64();
+            if span > 0. {
+                self.rate = (value - self.value) as f64 / span;
+            }
+            self.value = value;
+            self.changed = time;
+        }
+        self.sampled = time;
+        self.reported = (self.reported + self.rate * elapsed).min(value as f64);
+        self.reported as u64
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::read::TaskGroupStats;
+
+/// The full stats of every monitored process at one moment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// When the stats were sampled; rates between two snapshots are over the time between them
+    pub time: SystemTime,
+    pub stats: BTreeMap<usize, TaskGroupStats>,
+}
+impl Snapshot {
+    pub fn new(stats: BTreeMap<usize, TaskGroupStats>) -> Self {
+        Self {
+            time: SystemTime::now(),
+            stats,
+        }
+    }
+
+    /// Load the JSON written by [`Snapshot::save`].
+    ///
+    /// Monotonic times are not serialized; stamp the stats with [`Snapshot::stamped`] and
+    /// [`Snapshot::stamped_after`] before computing rates between snapshots.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    /// Write the snapshot as JSON to a new file in `dir` named after its time
+    pub fn save(&self, dir: &Path) -> io::Result<PathBuf> {
+        let timestamp = self
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let path = dir.join(format!(
+            "pidstat-snapshot-{secs}.{millis:03}.json",
+            secs = timestamp.as_secs(),
+            millis = timestamp.subsec_millis()
+        ));
+        let mut file = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.flush()?;
+        Ok(path)
+    }
+
+    /// The stats stamped so that they were sampled `self.time - prev.time` after those of `prev`
+    /// stamped at `prev_time`, or at the same time if `self` is not newer
+    pub fn stamped_after(
+        &self,
+        prev: &Snapshot,
+        prev_time: Instant,
+    ) -> BTreeMap<usize, TaskGroupStats> {
+        let elapsed = self.time.duration_since(prev.time).unwrap_or_default();
+        stamp(&self.stats, prev_time + elapsed)
+    }
+
+    /// The stats stamped with `time`
+    pub fn stamped(&self, time: Instant) -> BTreeMap<usize, TaskGroupStats> {
+        stamp(&self.stats, time)
+    }
+}
+
+fn stamp(
+    stats: &BTreeMap<usize, TaskGroupStats>,
+    time: Instant,
+) -> BTreeMap<usize, TaskGroupStats> {
+    stats
+        .iter()
+        .map(|(&pid, stats)| {
+            let mut stats = stats.clone();
+            stats.process.components = stats.process.components.at(time);
+            for task in stats.task.values_mut() {
+                task.components = task.components.at(time);
+            }
+            (pid, stats)
+        })
+        .collect()
+}
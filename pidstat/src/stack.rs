@@ -1,18 +1,24 @@
 use core::fmt;
 use std::time::Instant;
 
-use common::value::{MemoryUnit, U64ColorStatsDisplay};
+use common::value::{write_columns, MemoryUnit, U64ColorStatsDisplay};
+use serde::{Deserialize, Serialize};
 
-use crate::process::{
-    CommandDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId, TidDisplayOption,
+use crate::{
+    columns::ColumnHeaderDisplay,
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StackStats {
     /// The amount of memory in kilobytes reserved for the task as stack, but not necessarily used
     pub stk_size: u64,
     /// The amount of memory in kilobytes used as stack, referenced by the task
     pub stk_ref: u64,
+    #[serde(skip, default = "Instant::now")]
     pub time: Instant,
 }
 
@@ -22,8 +28,16 @@ pub struct StackStatsHeaderDisplay {
 }
 impl fmt::Display for StackStatsHeaderDisplay {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", IdHeaderDisplay { tid: self.tid })?;
-        writeln!(f, " StkSize  StkRef  Command")?;
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["StkSize", "StkRef"],
+                width: 7,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
         Ok(())
     }
 }
@@ -31,6 +45,8 @@ impl fmt::Display for StackStatsHeaderDisplay {
 #[derive(Debug, Clone)]
 pub struct StackStatsValueDisplay<'a> {
     pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
     pub id: &'a ProcessId,
     pub curr_stats: &'a StackStats,
 }
@@ -39,18 +55,23 @@ impl<'a> fmt::Display for StackStatsValueDisplay<'a> {
         let display = IdValueDisplay {
             process: self.id,
             tid: self.tid,
+            pid_color: self.pid_color,
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
         let display = U64ColorStatsDisplay {
             values: &[self.curr_stats.stk_size, self.curr_stats.stk_ref],
             width: 7,
             unit: Some(MemoryUnit::Kilobytes),
         };
-        write!(f, "{}", display)?;
+        write_columns(f, &display)?;
 
-        let display = CommandDisplay { process: self.id };
-        writeln!(f, "{}", display)?;
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
 
         Ok(())
     }
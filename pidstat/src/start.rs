@@ -0,0 +1,90 @@
+use core::fmt;
+use std::time::SystemTime;
+
+use common::value::{int_stat_color, normal_color, start_column, write_columns};
+use time::OffsetDateTime;
+
+use crate::{
+    columns::ColumnHeaderDisplay,
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct StartHeaderDisplay {
+    pub tid: TidDisplayOption,
+}
+impl fmt::Display for StartHeaderDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_columns(f, &IdHeaderDisplay { tid: self.tid })?;
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["START"],
+                width: 8,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StartValueDisplay<'a> {
+    pub tid: TidDisplayOption,
+    /// Whether to color the ID and command by the PID
+    pub pid_color: bool,
+    pub id: &'a ProcessId,
+    pub start: SystemTime,
+}
+impl<'a> fmt::Display for StartValueDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = IdValueDisplay {
+            process: self.id,
+            tid: self.tid,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+
+        start_column(f)?;
+        write!(
+            f,
+            "{start} {value:>8}{end}",
+            start = int_stat_color(),
+            value = start_time(self.start, SystemTime::now()),
+            end = normal_color()
+        )?;
+
+        let display = CommandDisplay {
+            process: self.id,
+            pid_color: self.pid_color,
+        };
+        write_columns(f, &display)?;
+        writeln!(f)?;
+
+        Ok(())
+    }
+}
+
+/// Format `start` like the STIME of `ps`, in UTC: the time if it is on the day of `now`, else the
+/// month and day if it is in the year of `now`, else the year
+fn start_time(start: SystemTime, now: SystemTime) -> String {
+    let start = OffsetDateTime::from(start);
+    let now = OffsetDateTime::from(now);
+    if start.date() == now.date() {
+        return format!(
+            "{:02}:{:02}:{:02}",
+            start.hour(),
+            start.minute(),
+            start.second()
+        );
+    }
+    if start.year() == now.year() {
+        let month = start.month().to_string();
+        return format!("{}{:02}", &month[..3], start.day());
+    }
+    start.year().to_string()
+}
@@ -0,0 +1,150 @@
+use core::fmt;
+use std::{collections::BTreeMap, time::Instant};
+
+use common::value::{int_stat_color, normal_color, start_column, write_columns};
+
+use crate::{
+    columns::ColumnHeaderDisplay,
+    cpu::CpuStats,
+    io::IoStats,
+    process::{
+        CommandDisplay, CommandHeaderDisplay, IdHeaderDisplay, IdValueDisplay, ProcessId,
+        TidDisplayOption,
+    },
+};
+
+/// The disk IO of every monitored process added up over the whole run
+#[derive(Debug, Clone, Default)]
+pub struct IoSummary {
+    read_bytes: u64,
+    write_bytes: u64,
+    /// The times of the first and the last sample
+    span: Option<(Instant, Instant)>,
+}
+impl IoSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the IO of a process between two of its samples
+    pub fn add(&mut self, prev: &IoStats, curr: &IoStats) {
+        // A reset counter would otherwise count as a huge amount of IO
+        self.read_bytes += curr.read_bytes.saturating_sub(prev.read_bytes);
+        self.write_bytes += curr.write_bytes.saturating_sub(prev.write_bytes);
+        self.span = Some(match self.span {
+            Some((start, end)) => (start.min(prev.time), end.max(curr.time)),
+            None => (prev.time, curr.time),
+        });
+    }
+}
+impl fmt::Display for IoSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elapsed = self
+            .span
+            .map(|(start, end)| (end - start).as_secs_f64())
+            .unwrap_or_default();
+        writeln!(f)?;
+        writeln!(f, "Summary of disk IO over {elapsed:.1} s")?;
+        writeln!(
+            f,
+            "Total:   {read:>10} read {written:>10} written",
+            read = format!("{:.1} kB", self.read_bytes as f64 / 1024.),
+            written = format!("{:.1} kB", self.write_bytes as f64 / 1024.),
+        )?;
+        let rate = |bytes: u64| match 0. < elapsed {
+            true => bytes as f64 / 1024. / elapsed,
+            false => 0.,
+        };
+        writeln!(
+            f,
+            "Average: {read:>10} read {written:>10} written",
+            read = format!("{:.1} kB/s", rate(self.read_bytes)),
+            written = format!("{:.1} kB/s", rate(self.write_bytes)),
+        )?;
+        Ok(())
+    }
+}
+
+/// The run queue wait of every monitored process over each interval of the run, to tell the tail
+/// of the scheduling latency that the average `%wait` hides
+#[derive(Debug, Clone, Default)]
+pub struct WaitSummary {
+    /// The `%wait` of every interval and the latest ID, by PID
+    waits: BTreeMap<usize, (ProcessId, Vec<f64>)>,
+}
+impl WaitSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the wait of the process `id` between two of its samples
+    pub fn add(&mut self, id: &ProcessId, prev: &CpuStats, curr: &CpuStats) {
+        let interval = (curr.time - prev.time).as_secs_f64();
+        if interval <= 0. {
+            return;
+        }
+        let wait = curr.wait_time.saturating_sub(prev.wait_time) as f64
+            / curr.clock_ticks_per_second as f64
+            / interval
+            * 100.;
+        let (latest, waits) = self
+            .waits
+            .entry(id.proc_id.pid)
+            .or_insert_with(|| (id.clone(), vec![]));
+        *latest = id.clone();
+        waits.push(wait);
+    }
+}
+impl fmt::Display for WaitSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.waits.is_empty() {
+            return Ok(());
+        }
+        let tid = TidDisplayOption::Pid;
+        writeln!(f)?;
+        writeln!(f, "Summary of %wait over each interval")?;
+        write_columns(f, &IdHeaderDisplay { tid })?;
+        write_columns(
+            f,
+            &ColumnHeaderDisplay {
+                names: &["p50%wait", "p95%wait", "p99%wait"],
+                width: 9,
+            },
+        )?;
+        write_columns(f, &CommandHeaderDisplay)?;
+        writeln!(f)?;
+        for (id, waits) in self.waits.values() {
+            let display = IdValueDisplay {
+                process: id,
+                tid,
+                pid_color: false,
+            };
+            write_columns(f, &display)?;
+            let mut waits = waits.clone();
+            waits.sort_by(f64::total_cmp);
+            for p in [50., 95., 99.] {
+                start_column(f)?;
+                write!(
+                    f,
+                    "{start} {value:9.2}{end}",
+                    start = int_stat_color(),
+                    value = percentile(&waits, p),
+                    end = normal_color()
+                )?;
+            }
+            let display = CommandDisplay {
+                process: id,
+                pid_color: false,
+            };
+            write_columns(f, &display)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The nearest-rank percentile `p` of the non-empty `sorted`
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100. * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,71 @@
+#![cfg(target_os = "linux")]
+
+use std::{
+    io,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use common::value::strip_colors;
+
+/// Run a script that sleeps until killed, whose processes take the name of its file
+fn spawn_named(path: &Path) -> Child {
+    loop {
+        match Command::new(path).spawn() {
+            // A thread of another test forked while the file was open for writing
+            Err(e) if e.kind() == io::ErrorKind::ExecutableFileBusy => continue,
+            child => return child.unwrap(),
+        }
+    }
+}
+
+/// The PIDs of the rows of the tables
+fn reported_pid(stdout: &str) -> Vec<u32> {
+    strip_colors(stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1)?.parse().ok())
+        .collect()
+}
+
+#[test]
+fn processes_exit_and_appear_between_samples() {
+    let name = format!("pschurn{}", std::process::id());
+    let path = std::env::temp_dir().join(&name);
+    std::fs::write(&path, "#!/bin/sh\nwhile :; do sleep 1; done\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut exiting = spawn_named(&path);
+    let pidstat = Command::new(env!("CARGO_BIN_EXE_pidstat"))
+        .args(["-u", "-r", "-G", &name, "--name-match", "exact"])
+        .args(["0.2", "15"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(700));
+    let mut appearing = spawn_named(&path);
+    thread::sleep(Duration::from_millis(700));
+    exiting.kill().unwrap();
+    exiting.wait().unwrap();
+
+    let output = pidstat.wait_with_output().unwrap();
+    appearing.kill().unwrap();
+    appearing.wait().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let reported = reported_pid(&stdout);
+    assert!(reported.contains(&exiting.id()), "{stdout}");
+    assert!(reported.contains(&appearing.id()), "{stdout}");
+    // The exited process is dropped rather than reported with stale stats
+    let last_rows = stdout.lines().rev().take(4).collect::<Vec<_>>().join("\n");
+    assert!(
+        !reported_pid(&last_rows).contains(&exiting.id()),
+        "{stdout}"
+    );
+}